@@ -54,6 +54,99 @@ pub enum SlicerErrors {
     ///Error during tower generation
     SliceGeneration,
 
+    ///Error loading the OBJ file
+    ObjLoadError,
+
+    ///Error loading the PLY file
+    PlyLoadError,
+
+    ///Error loading the AMF file
+    AmfLoadError,
+
+    ///No loader supports the file's format
+    UnknownFileFormat {
+        ///File that could not be loaded
+        filepath: String,
+    },
+
+    ///The objects could not all be arranged on the bed
+    ObjectsDoNotFit {
+        ///Name of the first object that could not be placed
+        name: String,
+    },
+
+    ///Part of an object is outside of the build volume
+    ObjectOutOfBounds {
+        ///Name of the object that is out of bounds
+        name: String,
+
+        ///The axis the object is out of bounds on
+        axis: char,
+
+        ///How far outside of the build volume the object is in mm
+        distance: f64,
+    },
+
+    ///Objects printed one at a time are closer than the extruder clearance radius
+    SequentialPrintingCollision {
+        ///Name of the first object
+        first: String,
+
+        ///Name of the second object
+        second: String,
+    },
+
+    ///More than one object printed one at a time is taller than the gantry
+    SequentialPrintingTooTall {
+        ///Name of the first tall object
+        first: String,
+
+        ///Name of the second tall object
+        second: String,
+    },
+
+    ///The cut plane doesn't pass through the object
+    CutPlaneMissesObject {
+        ///Name of the object being cut
+        name: String,
+    },
+
+    ///The edges cut by the plane don't form closed loops so the cut faces can't be filled
+    CutNotClosed {
+        ///Name of the object being cut
+        name: String,
+    },
+
+    ///Error during tower generation of a named object
+    ObjectTowerGeneration {
+        ///Name of the object
+        name: String,
+    },
+
+    ///A move that isn't part of an object, such as the skirt or brim, is outside of the build volume
+    MoveOutOfBounds {
+        ///The axis the move is out of bounds on
+        axis: char,
+
+        ///How far outside of the build volume the move is in mm
+        distance: f64,
+    },
+
+    ///A setting that must be positive is zero or negative
+    SettingNotPositive {
+        ///Setting that was not positive
+        setting: String,
+    },
+
+    ///A layer printed as a vase mode spiral has more than one island
+    VaseModeMultipleIslands {
+        ///Height of the top of the layer in mm
+        height: f64,
+
+        ///Number of islands in the layer
+        islands: usize,
+    },
+
     ///Another error, here for plugins to use
     UnspecifiedError(String),
 }
@@ -98,6 +191,48 @@ impl SlicerErrors {
             SlicerErrors::SliceGeneration => {
                 (0x100B,"There was a issue ordering the polygon for slicing. Try repairing your Model.".to_string())
             }
+            SlicerErrors::ObjLoadError => {
+                (0x100C,"There was a issue loading the OBJ file.".to_string())
+            }
+            SlicerErrors::PlyLoadError => {
+                (0x100D,"There was a issue loading the PLY file. Only vertex positions and polygon faces are supported.".to_string())
+            }
+            SlicerErrors::AmfLoadError => {
+                (0x100E,"There was a issue loading the AMF file.".to_string())
+            }
+            SlicerErrors::UnknownFileFormat { filepath } => {
+                (0x100F,format!("Could not load object file \"{}\". The file format is not supported.",filepath))
+            }
+            SlicerErrors::ObjectsDoNotFit { name } => {
+                (0x1010,format!("Could not arrange object \"{}\" on the bed. There is not enough space for all the objects.",name))
+            }
+            SlicerErrors::ObjectOutOfBounds { name, axis, distance } => {
+                (0x1011,format!("Object \"{}\" is {:.3} mm outside of the build volume in {}.",name,distance,axis))
+            }
+            SlicerErrors::SequentialPrintingCollision { first, second } => {
+                (0x1012,format!("Objects \"{}\" and \"{}\" are closer than the extruder clearance radius so they can't be printed one at a time. Reordering the objects can't fix this, move them further apart.",first,second))
+            }
+            SlicerErrors::SequentialPrintingTooTall { first, second } => {
+                (0x1013,format!("Objects \"{}\" and \"{}\" are both taller than the gantry height. Only the last object printed one at a time can be taller than the gantry.",first,second))
+            }
+            SlicerErrors::CutPlaneMissesObject { name } => {
+                (0x1014,format!("The cut plane does not pass through object \"{}\".",name))
+            }
+            SlicerErrors::CutNotClosed { name } => {
+                (0x1015,format!("The cut through object \"{}\" does not form closed loops. Try repairing your Model.",name))
+            }
+            SlicerErrors::ObjectTowerGeneration { name } => {
+                (0x1016,format!("Error Creating Tower for object \"{}\". Model most likely needs repair. Please Repair and run again.",name))
+            }
+            SlicerErrors::MoveOutOfBounds { axis, distance } => {
+                (0x1017,format!("The skirt or brim is {:.3} mm outside of the build volume in {}. Move the objects away from the edge of the bed.",distance,axis))
+            }
+            SlicerErrors::SettingNotPositive { setting } => {
+                (0x1018,format!("The setting \"{}\" must be greater than zero.",setting))
+            }
+            SlicerErrors::VaseModeMultipleIslands { height, islands } => {
+                (0x1019,format!("Vase mode can only print one island per layer but the layer at {:.3} mm has {} islands.",height,islands))
+            }
             SlicerErrors::UnspecifiedError(err_string) => {
                 (0xFFFFFFFF,format!("Third Party Error. {}",err_string))
             }
@@ -0,0 +1,359 @@
+use crate::loader::*;
+use std::io::{Cursor, Read};
+use xml::attribute::OwnedAttribute;
+use xml::reader::{EventReader, XmlEvent};
+
+#[derive(Debug, Default)]
+struct AMFObject {
+    id: usize,
+    vertices: Vec<Vertex>,
+    triangles: Vec<IndexedTriangle>,
+}
+
+#[derive(Debug, Default)]
+struct AMFConstellation {
+    id: usize,
+    instances: Vec<AMFInstance>,
+}
+
+#[derive(Debug, Default)]
+struct AMFInstance {
+    objectid: usize,
+    deltax: f64,
+    deltay: f64,
+    deltaz: f64,
+    rx: f64,
+    ry: f64,
+    rz: f64,
+}
+
+///The contents of an AMF file. Materials, textures and metadata are ignored.
+#[derive(Debug, Default)]
+struct AMFFile {
+    unit: Option<String>,
+    objects: Vec<AMFObject>,
+    constellations: Vec<AMFConstellation>,
+}
+
+///Loader for AMF files, both plain and zip compressed
+pub struct AMFLoader {}
+
+impl Loader for AMFLoader {
+    fn extensions(&self) -> &'static [&'static str] {
+        &["amf"]
+    }
+
+    fn detect(&self, header: &[u8], _file_size: u64) -> bool {
+        match zip_entry_names(header).first() {
+            Some(name) => name.ends_with(b".amf"),
+            None => header.windows(4).any(|window| window == b"<amf"),
+        }
+    }
+
+    fn load(&self, filepath: &str) -> Result<Vec<LoadedModel>, SlicerErrors> {
+        let mut contents = vec![];
+        std::fs::File::open(filepath)
+            .and_then(|mut file| file.read_to_end(&mut contents))
+            .map_err(|_| SlicerErrors::ObjectFileNotFound {
+                filepath: filepath.to_string(),
+            })?;
+
+        parse_amf(&contents)
+    }
+
+    fn load_from_bytes(&self, contents: &[u8]) -> Result<Vec<LoadedModel>, SlicerErrors> {
+        parse_amf(contents)
+    }
+}
+
+fn parse_amf(contents: &[u8]) -> Result<Vec<LoadedModel>, SlicerErrors> {
+    let amf = if contents.starts_with(b"PK") {
+        //Zipped AMF files contain a single amf file
+        let mut archive =
+            zip::ZipArchive::new(Cursor::new(contents)).map_err(|_| SlicerErrors::AmfLoadError)?;
+
+        let amf_path = archive
+            .file_names()
+            .find(|name| !name.ends_with('/'))
+            .ok_or(SlicerErrors::AmfLoadError)?
+            .to_string();
+
+        let amf_file = archive
+            .by_name(&amf_path)
+            .map_err(|_| SlicerErrors::AmfLoadError)?;
+
+        read_amf_xml(amf_file)
+    } else {
+        read_amf_xml(contents)
+    }?;
+
+    let scale = match &amf.unit {
+        Some(unit) => unit_to_millimeter_scale(unit).ok_or(SlicerErrors::AmfLoadError)?,
+        None => 1.0,
+    };
+
+    //Constellations that are not instanced by another constellation are the top level models
+    let top_level_constellations: Vec<&AMFConstellation> = amf
+        .constellations
+        .iter()
+        .filter(|constellation| {
+            !amf.constellations
+                .iter()
+                .flat_map(|other| other.instances.iter())
+                .any(|instance| instance.objectid == constellation.id)
+        })
+        .collect();
+
+    let models = if top_level_constellations.is_empty() {
+        amf.objects
+            .iter()
+            .map(handle_object)
+            .collect::<Result<Vec<_>, SlicerErrors>>()?
+    } else {
+        let mut models = vec![];
+        for constellation in top_level_constellations {
+            models.append(&mut handle_constellation(constellation, &amf, 0)?);
+        }
+        models
+    };
+
+    if models.is_empty() {
+        return Err(SlicerErrors::AmfLoadError);
+    }
+
+    Ok(models
+        .into_iter()
+        .map(|(mut vertices, triangles)| {
+            for vert in vertices.iter_mut() {
+                vert.x *= scale;
+                vert.y *= scale;
+                vert.z *= scale;
+            }
+
+            let triangles = triangles
+                .into_iter()
+                .map(|tri| rotate_lowest_vertex_first(tri, &vertices))
+                .collect();
+
+            (vertices, triangles)
+        })
+        .collect())
+}
+
+///Read the xml document. The top level elements can be in any order so the file is walked element by element.
+fn read_amf_xml<R: Read>(reader: R) -> Result<AMFFile, SlicerErrors> {
+    let mut amf = AMFFile::default();
+
+    //Stack of the currently open element names
+    let mut path: Vec<String> = vec![];
+    let mut text = String::new();
+
+    for event in EventReader::new(reader) {
+        match event.map_err(|_| SlicerErrors::AmfLoadError)? {
+            XmlEvent::StartElement {
+                name, attributes, ..
+            } => {
+                let parent = path.last().map(|p| p.as_str());
+                match (parent, name.local_name.as_str()) {
+                    (None, "amf") => {
+                        amf.unit = get_attribute(&attributes, "unit").map(|u| u.to_string());
+                    }
+                    (Some("amf"), "object") => amf.objects.push(AMFObject {
+                        id: parse_attribute(&attributes, "id")?,
+                        ..Default::default()
+                    }),
+                    (Some("vertices"), "vertex") => {
+                        get_last(&mut amf.objects)?.vertices.push(Vertex::default())
+                    }
+                    (Some("volume"), "triangle") => get_last(&mut amf.objects)?
+                        .triangles
+                        .push(IndexedTriangle::default()),
+                    (Some("amf"), "constellation") => amf.constellations.push(AMFConstellation {
+                        id: parse_attribute(&attributes, "id")?,
+                        ..Default::default()
+                    }),
+                    (Some("constellation"), "instance") => get_last(&mut amf.constellations)?
+                        .instances
+                        .push(AMFInstance {
+                            objectid: parse_attribute(&attributes, "objectid")?,
+                            ..Default::default()
+                        }),
+                    _ => {}
+                }
+
+                path.push(name.local_name);
+                text.clear();
+            }
+            XmlEvent::Characters(chars) => text.push_str(&chars),
+            XmlEvent::EndElement { name } => {
+                path.pop();
+                let parent = path.last().map(|p| p.as_str());
+                match (parent, name.local_name.as_str()) {
+                    (Some("coordinates"), coord @ ("x" | "y" | "z")) => {
+                        let value = parse_text(&text)?;
+                        let vertex = get_last(&mut get_last(&mut amf.objects)?.vertices)?;
+                        match coord {
+                            "x" => vertex.x = value,
+                            "y" => vertex.y = value,
+                            _ => vertex.z = value,
+                        }
+                    }
+                    (Some("triangle"), index @ ("v1" | "v2" | "v3")) => {
+                        let value = parse_text(&text)?;
+                        let triangle = get_last(&mut get_last(&mut amf.objects)?.triangles)?;
+                        match index {
+                            "v1" => triangle.verts[0] = value,
+                            "v2" => triangle.verts[1] = value,
+                            _ => triangle.verts[2] = value,
+                        }
+                    }
+                    (Some("instance"), field) => {
+                        let instance = get_last(&mut get_last(&mut amf.constellations)?.instances)?;
+                        match field {
+                            "deltax" => instance.deltax = parse_text(&text)?,
+                            "deltay" => instance.deltay = parse_text(&text)?,
+                            "deltaz" => instance.deltaz = parse_text(&text)?,
+                            "rx" => instance.rx = parse_text(&text)?,
+                            "ry" => instance.ry = parse_text(&text)?,
+                            "rz" => instance.rz = parse_text(&text)?,
+                            _ => {}
+                        }
+                    }
+                    _ => {}
+                }
+                text.clear();
+            }
+            _ => {}
+        }
+    }
+
+    Ok(amf)
+}
+
+fn get_last<T>(list: &mut [T]) -> Result<&mut T, SlicerErrors> {
+    list.last_mut().ok_or(SlicerErrors::AmfLoadError)
+}
+
+fn get_attribute<'a>(attributes: &'a [OwnedAttribute], name: &str) -> Option<&'a str> {
+    attributes
+        .iter()
+        .find(|attr| attr.name.local_name == name)
+        .map(|attr| attr.value.as_str())
+}
+
+fn parse_attribute<T: std::str::FromStr>(
+    attributes: &[OwnedAttribute],
+    name: &str,
+) -> Result<T, SlicerErrors> {
+    get_attribute(attributes, name)
+        .and_then(|value| value.trim().parse().ok())
+        .ok_or(SlicerErrors::AmfLoadError)
+}
+
+fn parse_text<T: std::str::FromStr>(text: &str) -> Result<T, SlicerErrors> {
+    text.trim().parse().map_err(|_| SlicerErrors::AmfLoadError)
+}
+
+fn handle_constellation(
+    constellation: &AMFConstellation,
+    amf: &AMFFile,
+    depth: usize,
+) -> Result<Vec<LoadedModel>, SlicerErrors> {
+    //Protect against constellations that instance themselves
+    if depth > amf.constellations.len() {
+        return Err(SlicerErrors::AmfLoadError);
+    }
+
+    let mut models = vec![];
+
+    for instance in &constellation.instances {
+        let mut instance_models =
+            if let Some(object) = amf.objects.iter().find(|obj| obj.id == instance.objectid) {
+                vec![handle_object(object)?]
+            } else if let Some(child) = amf
+                .constellations
+                .iter()
+                .find(|child| child.id == instance.objectid)
+            {
+                handle_constellation(child, amf, depth + 1)?
+            } else {
+                return Err(SlicerErrors::AmfLoadError);
+            };
+
+        let rotation = Transform::new_rotation_transform(instance.rx, instance.ry, instance.rz);
+
+        for (vertices, _) in instance_models.iter_mut() {
+            for vert in vertices.iter_mut() {
+                *vert = &rotation * *vert;
+                vert.x += instance.deltax;
+                vert.y += instance.deltay;
+                vert.z += instance.deltaz;
+            }
+        }
+
+        models.append(&mut instance_models);
+    }
+
+    Ok(models)
+}
+
+fn handle_object(object: &AMFObject) -> Result<LoadedModel, SlicerErrors> {
+    if object
+        .triangles
+        .iter()
+        .flat_map(|tri| tri.verts.iter())
+        .any(|index| *index >= object.vertices.len())
+    {
+        Err(SlicerErrors::AmfLoadError)
+    } else {
+        Ok((object.vertices.clone(), object.triangles.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TETRAHEDRON_OBJECT: &str = "<object id=\"0\"><mesh><vertices>\
+        <vertex><coordinates><x>0</x><y>0</y><z>0</z></coordinates></vertex>\
+        <vertex><coordinates><x>1</x><y>0</y><z>0</z></coordinates></vertex>\
+        <vertex><coordinates><x>0</x><y>1</y><z>0</z></coordinates></vertex>\
+        <vertex><coordinates><x>0</x><y>0</y><z>1</z></coordinates></vertex>\
+        </vertices>\
+        <volume><triangle><v1>0</v1><v2>2</v2><v3>1</v3></triangle>\
+        <triangle><v1>0</v1><v2>1</v2><v3>3</v3></triangle></volume>\
+        <volume><triangle><v1>1</v1><v2>2</v2><v3>3</v3></triangle>\
+        <triangle><v1>0</v1><v2>3</v2><v3>2</v3></triangle></volume>\
+        </mesh></object>";
+
+    #[test]
+    fn volumes_are_merged() {
+        let amf = format!(
+            "<?xml version=\"1.0\"?><amf unit=\"inch\">{}</amf>",
+            TETRAHEDRON_OBJECT
+        );
+        let models = parse_amf(amf.as_bytes()).unwrap();
+
+        assert_eq!(models.len(), 1);
+        assert_eq!(models[0].1.len(), 4);
+        assert_eq!(models[0].0[1].x, 25.4);
+    }
+
+    #[test]
+    fn constellation_instances() {
+        let amf = format!(
+            "<amf>{}\
+            <constellation id=\"1\">\
+            <instance objectid=\"0\"><deltax>10</deltax><deltay>0</deltay><deltaz>0</deltaz><rx>0</rx><ry>0</ry><rz>90</rz></instance>\
+            <instance objectid=\"0\"><deltax>-10</deltax><deltay>0</deltay><deltaz>0</deltaz><rx>0</rx><ry>0</ry><rz>0</rz></instance>\
+            </constellation></amf>",
+            TETRAHEDRON_OBJECT
+        );
+        let models = parse_amf(amf.as_bytes()).unwrap();
+
+        assert_eq!(models.len(), 2);
+        assert!((models[0].0[1].x - 10.0).abs() < 0.00001);
+        assert!((models[0].0[1].y - 1.0).abs() < 0.00001);
+        assert_eq!(models[1].0[1].x, -9.0);
+    }
+}
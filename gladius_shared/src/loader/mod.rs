@@ -1,19 +1,336 @@
 #![deny(missing_docs)]
 
 use crate::error::*;
+use crate::settings::PartialSettings;
 use crate::types::*;
+use std::io::Read;
+use std::path::Path;
 
+mod amf;
+mod obj;
+mod ply;
 mod stl;
 mod threemf;
 
+pub use amf::AMFLoader;
+pub use obj::OBJLoader;
+pub use ply::PLYLoader;
 pub use stl::STLLoader;
 pub use threemf::ThreeMFLoader;
 
+///The vertices and triangles of a loaded model
+pub type LoadedModel = (Vec<Vertex>, Vec<IndexedTriangle>);
+
+///The name and metadata of a model stored in the model file
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ModelMetadata {
+    ///The name of the model
+    pub name: Option<String>,
+
+    ///Other metadata of the model as name and value pairs, such as its part number
+    pub values: Vec<(String, String)>,
+}
+
+///The name and metadata of a loaded model with its vertices and triangles
+pub type LoadedModelWithMetadata = (ModelMetadata, Vec<Vertex>, Vec<IndexedTriangle>);
+
 ///Loader trait to define loading in a file type of a model into a triangles and vertices
 pub trait Loader {
     ///Load a specifc file
-    fn load(
+    fn load(&self, filepath: &str) -> Result<Vec<LoadedModel>, SlicerErrors>;
+
+    ///Load a file from its contents held in memory, loaders that only load from the file system return an unknown file format error
+    fn load_from_bytes(&self, _contents: &[u8]) -> Result<Vec<LoadedModel>, SlicerErrors> {
+        Err(SlicerErrors::UnknownFileFormat {
+            filepath: MEMORY_FILEPATH.to_string(),
+        })
+    }
+
+    ///Load a file from its contents held in memory along with the name and metadata of each model if the file format stores them
+    fn load_from_bytes_with_metadata(
+        &self,
+        contents: &[u8],
+    ) -> Result<Vec<LoadedModelWithMetadata>, SlicerErrors> {
+        Ok(self
+            .load_from_bytes(contents)?
+            .into_iter()
+            .map(|(vertices, triangles)| (ModelMetadata::default(), vertices, triangles))
+            .collect())
+    }
+
+    ///Load a specifc file along with the name and metadata of each model if the file format stores them
+    fn load_with_metadata(
         &self,
         filepath: &str,
-    ) -> Result<Vec<(Vec<Vertex>, Vec<IndexedTriangle>)>, SlicerErrors>;
+    ) -> Result<Vec<LoadedModelWithMetadata>, SlicerErrors> {
+        Ok(self
+            .load(filepath)?
+            .into_iter()
+            .map(|(vertices, triangles)| (ModelMetadata::default(), vertices, triangles))
+            .collect())
+    }
+
+    ///Load the slicer settings embedded in a specifc file if the file format stores them
+    fn load_settings(&self, _filepath: &str) -> Result<Option<PartialSettings>, SlicerErrors> {
+        Ok(None)
+    }
+
+    ///Load the slicer settings embedded in a file held in memory if the file format stores them
+    fn load_settings_from_bytes(
+        &self,
+        _contents: &[u8],
+    ) -> Result<Option<PartialSettings>, SlicerErrors> {
+        Ok(None)
+    }
+
+    ///The file extensions to use this loader for when the contents of the file are not recognised
+    fn extensions(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    ///Check if the start of a file is in a format this loader can load
+    fn detect(&self, _header: &[u8], _file_size: u64) -> bool {
+        false
+    }
+}
+
+///The file path used in errors for files loaded from memory
+pub const MEMORY_FILEPATH: &str = "memory";
+
+///The number of bytes from the start of the file used to detect the file format
+pub const DETECTION_HEADER_SIZE: usize = 512;
+
+///The loaders available to load model files
+pub struct LoaderRegistry {
+    loaders: Vec<Box<dyn Loader>>,
+}
+
+impl LoaderRegistry {
+    ///Create a registry without any loaders
+    pub fn empty() -> Self {
+        LoaderRegistry { loaders: vec![] }
+    }
+
+    ///Add a loader to the registry. Loaders registered later are checked first so they can replace existing loaders.
+    pub fn register(&mut self, loader: Box<dyn Loader>) {
+        self.loaders.push(loader);
+    }
+
+    ///Get the loader for a file. The format is detected from the contents of the file with the extension used when the contents are not recognised.
+    pub fn get_loader(&self, filepath: &str) -> Result<&dyn Loader, SlicerErrors> {
+        let file_not_found = || SlicerErrors::ObjectFileNotFound {
+            filepath: filepath.to_string(),
+        };
+
+        let file = std::fs::File::open(filepath).map_err(|_| file_not_found())?;
+        let file_size = file.metadata().map_err(|_| file_not_found())?.len();
+
+        let mut header = vec![];
+        file.take(DETECTION_HEADER_SIZE as u64)
+            .read_to_end(&mut header)
+            .map_err(|_| file_not_found())?;
+
+        let extension = Path::new(filepath)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_lowercase());
+
+        self.detect_loader(&header, file_size)
+            .or_else(|| {
+                let extension = extension?;
+                self.loaders
+                    .iter()
+                    .rev()
+                    .find(|loader| loader.extensions().contains(&extension.as_str()))
+                    .map(|loader| loader.as_ref())
+            })
+            .ok_or_else(|| SlicerErrors::UnknownFileFormat {
+                filepath: filepath.to_string(),
+            })
+    }
+
+    ///Get the loader for a file held in memory, the format is detected from the contents
+    pub fn get_loader_from_contents(&self, contents: &[u8]) -> Option<&dyn Loader> {
+        let header = &contents[..contents.len().min(DETECTION_HEADER_SIZE)];
+
+        self.detect_loader(header, contents.len() as u64)
+    }
+
+    fn detect_loader(&self, header: &[u8], file_size: u64) -> Option<&dyn Loader> {
+        self.loaders
+            .iter()
+            .rev()
+            .find(|loader| loader.detect(header, file_size))
+            .map(|loader| loader.as_ref())
+    }
+}
+
+impl Default for LoaderRegistry {
+    fn default() -> Self {
+        let mut registry = LoaderRegistry::empty();
+
+        registry.register(Box::new(OBJLoader {}));
+        registry.register(Box::new(PLYLoader {}));
+        registry.register(Box::new(STLLoader {}));
+        registry.register(Box::new(ThreeMFLoader {}));
+        registry.register(Box::new(AMFLoader {}));
+
+        registry
+    }
+}
+
+///Get the names of the files in a zip archive from the local file headers in the start of the archive.
+/// Entries whose sizes are written after their data can't be skipped by size, so every local file header signature is read.
+pub(crate) fn zip_entry_names(header: &[u8]) -> Vec<&[u8]> {
+    if !header.starts_with(b"PK\x03\x04") || header.len() < 30 {
+        return vec![];
+    }
+
+    (0..=header.len() - 30)
+        .filter(|start| header[*start..].starts_with(b"PK\x03\x04"))
+        .filter_map(|start| {
+            let name_length = u16::from_le_bytes([header[start + 26], header[start + 27]]) as usize;
+            header.get(start + 30..start + 30 + name_length)
+        })
+        .collect()
+}
+
+///Rotate the triangle's indices so the lowest vertex is first while keeping the winding order
+pub fn rotate_lowest_vertex_first(
+    mut triangle: IndexedTriangle,
+    vertices: &[Vertex],
+) -> IndexedTriangle {
+    let v0 = vertices[triangle.verts[0]];
+    let v1 = vertices[triangle.verts[1]];
+    let v2 = vertices[triangle.verts[2]];
+
+    if v0 < v1 && v0 < v2 {
+        triangle
+    } else if v1 < v2 && v1 < v0 {
+        triangle.verts.rotate_left(1);
+        triangle
+    } else {
+        triangle.verts.rotate_left(2);
+        triangle
+    }
+}
+
+///Get the scale to convert a model file's unit into millimeters
+pub(crate) fn unit_to_millimeter_scale(unit: &str) -> Option<f64> {
+    match unit.to_lowercase().as_str() {
+        "micron" | "micrometer" => Some(0.001),
+        "millimeter" => Some(1.0),
+        "centimeter" => Some(10.0),
+        "meter" => Some(1000.0),
+        "inch" => Some(25.4),
+        "foot" | "feet" => Some(304.8),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detected_extension(contents: &[u8]) -> Option<&'static str> {
+        LoaderRegistry::default()
+            .get_loader_from_contents(contents)
+            .map(|loader| loader.extensions()[0])
+    }
+
+    ///The local file headers of a zip archive of empty files with the names
+    fn zip_header(names: &[&str]) -> Vec<u8> {
+        let mut header = vec![];
+        for name in names {
+            let start = header.len();
+            header.extend_from_slice(b"PK\x03\x04");
+            header.resize(start + 30, 0);
+            header[start + 26] = name.len() as u8;
+            header.extend_from_slice(name.as_bytes());
+        }
+        header
+    }
+
+    #[test]
+    fn formats_are_detected_from_contents() {
+        let mut binary_stl = b"solid but actually binary".to_vec();
+        binary_stl.resize(80, 0);
+        binary_stl.extend_from_slice(&1u32.to_le_bytes());
+        binary_stl.resize(84 + 50, 0);
+
+        assert_eq!(
+            detected_extension(b"solid cube\n  facet normal 0 0 1\n"),
+            Some("stl")
+        );
+        assert_eq!(detected_extension(&binary_stl), Some("stl"));
+        assert_eq!(detected_extension(b"# comment\n\nv 0 0 0\n"), Some("obj"));
+        assert_eq!(
+            detected_extension(b"ply\nformat ascii 1.0\nelement vertex 3\n"),
+            Some("ply")
+        );
+        assert_eq!(
+            detected_extension(b"<?xml version=\"1.0\"?>\n<amf unit=\"inch\">"),
+            Some("amf")
+        );
+        assert_eq!(detected_extension(&zip_header(&["model.amf"])), Some("amf"));
+        assert_eq!(
+            detected_extension(&zip_header(&["3D/3dmodel.model"])),
+            Some("3mf")
+        );
+        assert_eq!(
+            detected_extension(&zip_header(&["[Content_Types].xml", "_rels/.rels"])),
+            Some("3mf")
+        );
+        assert_eq!(detected_extension(&zip_header(&["readme.txt"])), None);
+        assert_eq!(detected_extension(b"not a model"), None);
+    }
+
+    #[test]
+    fn other_zip_files_are_unknown() {
+        let path = std::env::temp_dir().join("gladius_loader_other.zip");
+        std::fs::write(&path, zip_header(&["readme.txt", "data.bin"])).unwrap();
+        let path = path.to_str().unwrap();
+
+        assert!(matches!(
+            LoaderRegistry::default().get_loader(path),
+            Err(SlicerErrors::UnknownFileFormat { filepath }) if filepath == path
+        ));
+    }
+
+    struct FileOnlyLoader {}
+
+    impl Loader for FileOnlyLoader {
+        fn load(&self, _filepath: &str) -> Result<Vec<LoadedModel>, SlicerErrors> {
+            Ok(vec![])
+        }
+    }
+
+    #[test]
+    fn models_load_from_bytes() {
+        let stl = b"solid triangle\n\
+                    facet normal 0 0 1\n\
+                    outer loop\n\
+                    vertex 0 0 0\n\
+                    vertex 1 0 0\n\
+                    vertex 0 1 0\n\
+                    endloop\n\
+                    endfacet\n\
+                    endsolid triangle\n";
+        let registry = LoaderRegistry::default();
+        let loader = registry.get_loader_from_contents(stl).unwrap();
+        let models = loader.load_from_bytes_with_metadata(stl).unwrap();
+
+        assert_eq!(models.len(), 1);
+        assert_eq!(models[0].0, ModelMetadata::default());
+        assert_eq!(models[0].1.len(), 3);
+        assert_eq!(models[0].2.len(), 1);
+        assert!(matches!(loader.load_settings_from_bytes(stl), Ok(None)));
+
+        assert_eq!(
+            FileOnlyLoader {}.load_from_bytes(stl),
+            Err(SlicerErrors::UnknownFileFormat {
+                filepath: MEMORY_FILEPATH.to_string()
+            })
+        );
+    }
 }
@@ -0,0 +1,189 @@
+use crate::loader::*;
+use std::io::{BufRead, BufReader};
+
+///Loader for Wavefront OBJ files
+pub struct OBJLoader {}
+
+impl Loader for OBJLoader {
+    fn extensions(&self) -> &'static [&'static str] {
+        &["obj"]
+    }
+
+    fn detect(&self, header: &[u8], _file_size: u64) -> bool {
+        //OBJ has no signature so check the first statement is an OBJ statement
+        let first_statement = String::from_utf8_lossy(header)
+            .lines()
+            .map(|line| line.trim().to_string())
+            .find(|line| !line.is_empty() && !line.starts_with('#'))
+            .and_then(|line| line.split_whitespace().next().map(|str| str.to_string()));
+
+        matches!(
+            first_statement.as_deref(),
+            Some("v" | "vt" | "vn" | "f" | "o" | "g" | "s" | "mtllib" | "usemtl")
+        )
+    }
+
+    fn load(&self, filepath: &str) -> Result<Vec<LoadedModel>, SlicerErrors> {
+        let file = std::fs::OpenOptions::new()
+            .read(true)
+            .open(filepath)
+            .map_err(|_| SlicerErrors::ObjectFileNotFound {
+                filepath: filepath.to_string(),
+            })?;
+
+        parse_obj(BufReader::new(file))
+    }
+
+    fn load_from_bytes(&self, contents: &[u8]) -> Result<Vec<LoadedModel>, SlicerErrors> {
+        parse_obj(contents)
+    }
+}
+
+fn parse_obj<R: BufRead>(reader: R) -> Result<Vec<LoadedModel>, SlicerErrors> {
+    let mut file_vertices: Vec<Vertex> = vec![];
+    //Faces of each o/g group, indexing into the file wide vertex list
+    let mut groups: Vec<Vec<[usize; 3]>> = vec![];
+    let mut current_faces = vec![];
+
+    for line in reader.lines() {
+        let line = line.map_err(|_| SlicerErrors::ObjLoadError)?;
+        //Remove comments
+        let line = line.split('#').next().unwrap_or_default();
+        let mut tokens = line.split_whitespace();
+
+        match tokens.next() {
+            Some("v") => {
+                let coords: Vec<f64> = tokens
+                    .take(3)
+                    .map(|str| str.parse())
+                    .collect::<Result<_, _>>()
+                    .map_err(|_| SlicerErrors::ObjLoadError)?;
+
+                if coords.len() != 3 {
+                    return Err(SlicerErrors::ObjLoadError);
+                }
+
+                file_vertices.push(Vertex {
+                    x: coords[0],
+                    y: coords[1],
+                    z: coords[2],
+                });
+            }
+            Some("f") => {
+                let indices: Vec<usize> = tokens
+                    .map(|str| parse_face_index(str, file_vertices.len()))
+                    .collect::<Result<_, _>>()?;
+
+                if indices.len() < 3 {
+                    return Err(SlicerErrors::ObjLoadError);
+                }
+
+                //Fan triangulation of the polygon
+                for (second, third) in indices[1..].iter().zip(indices[2..].iter()) {
+                    current_faces.push([indices[0], *second, *third]);
+                }
+            }
+            Some("o") | Some("g") => {
+                groups.push(std::mem::take(&mut current_faces));
+            }
+            _ => {}
+        }
+    }
+
+    groups.push(current_faces);
+
+    let models: Vec<LoadedModel> = groups
+        .into_iter()
+        .filter(|faces| !faces.is_empty())
+        .map(|faces| {
+            //Only keep the vertices used by this group
+            let mut index_map = vec![None; file_vertices.len()];
+            let mut vertices = vec![];
+
+            let triangles: Vec<IndexedTriangle> = faces
+                .iter()
+                .map(|face| {
+                    let mut converted_tri = IndexedTriangle::default();
+                    for (vert, file_index) in converted_tri.verts.iter_mut().zip(face.iter()) {
+                        *vert = *index_map[*file_index].get_or_insert_with(|| {
+                            vertices.push(file_vertices[*file_index]);
+                            vertices.len() - 1
+                        });
+                    }
+                    converted_tri
+                })
+                .collect();
+
+            let triangles = triangles
+                .into_iter()
+                .map(|tri| rotate_lowest_vertex_first(tri, &vertices))
+                .collect();
+
+            (vertices, triangles)
+        })
+        .collect();
+
+    if models.is_empty() {
+        Err(SlicerErrors::ObjLoadError)
+    } else {
+        Ok(models)
+    }
+}
+
+///Convert a face vertex reference ( v, v/vt, v//vn or v/vt/vn ) into a zero based vertex index
+fn parse_face_index(token: &str, vertex_count: usize) -> Result<usize, SlicerErrors> {
+    let index: isize = token
+        .split('/')
+        .next()
+        .and_then(|str| str.parse().ok())
+        .ok_or(SlicerErrors::ObjLoadError)?;
+
+    let converted = if index < 0 {
+        //Negative indices are relative to the end of the current vertex list
+        vertex_count as isize + index
+    } else {
+        index - 1
+    };
+
+    if converted < 0 || converted as usize >= vertex_count {
+        Err(SlicerErrors::ObjLoadError)
+    } else {
+        Ok(converted as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quad_is_triangulated() {
+        let obj = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nf 1/1/1 2/2/2 3/3/3 4/4/4\n";
+        let models = parse_obj(obj.as_bytes()).unwrap();
+
+        assert_eq!(models.len(), 1);
+        assert_eq!(models[0].0.len(), 4);
+        assert_eq!(models[0].1.len(), 2);
+    }
+
+    #[test]
+    fn groups_are_separate_models() {
+        let obj = "v 0 0 0\nv 1 0 0\nv 0 1 0\nv 0 0 1\n\
+                   o first\nf 1 2 3\n\
+                   g second\nf -4 -3 -1\nf 2 3 4\n";
+        let models = parse_obj(obj.as_bytes()).unwrap();
+
+        assert_eq!(models.len(), 2);
+        assert_eq!(models[0].0.len(), 3);
+        assert_eq!(models[0].1.len(), 1);
+        assert_eq!(models[1].0.len(), 4);
+        assert_eq!(models[1].1.len(), 2);
+    }
+
+    #[test]
+    fn out_of_range_index_fails() {
+        let obj = "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 4\n";
+
+        assert_eq!(parse_obj(obj.as_bytes()), Err(SlicerErrors::ObjLoadError));
+    }
+}
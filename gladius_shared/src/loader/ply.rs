@@ -0,0 +1,367 @@
+use crate::loader::*;
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader};
+
+///Loader for PLY files in ascii or binary format
+pub struct PLYLoader {}
+
+impl Loader for PLYLoader {
+    fn extensions(&self) -> &'static [&'static str] {
+        &["ply"]
+    }
+
+    fn detect(&self, header: &[u8], _file_size: u64) -> bool {
+        header.starts_with(b"ply\n") || header.starts_with(b"ply\r\n")
+    }
+
+    fn load(&self, filepath: &str) -> Result<Vec<LoadedModel>, SlicerErrors> {
+        let file = std::fs::OpenOptions::new()
+            .read(true)
+            .open(filepath)
+            .map_err(|_| SlicerErrors::ObjectFileNotFound {
+                filepath: filepath.to_string(),
+            })?;
+
+        parse_ply(BufReader::new(file))
+    }
+
+    fn load_from_bytes(&self, contents: &[u8]) -> Result<Vec<LoadedModel>, SlicerErrors> {
+        parse_ply(contents)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum PlyFormat {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum PlyScalar {
+    Char,
+    UChar,
+    Short,
+    UShort,
+    Int,
+    UInt,
+    Float,
+    Double,
+}
+
+impl PlyScalar {
+    fn from_name(name: &str) -> Result<Self, SlicerErrors> {
+        match name {
+            "char" | "int8" => Ok(PlyScalar::Char),
+            "uchar" | "uint8" => Ok(PlyScalar::UChar),
+            "short" | "int16" => Ok(PlyScalar::Short),
+            "ushort" | "uint16" => Ok(PlyScalar::UShort),
+            "int" | "int32" => Ok(PlyScalar::Int),
+            "uint" | "uint32" => Ok(PlyScalar::UInt),
+            "float" | "float32" => Ok(PlyScalar::Float),
+            "double" | "float64" => Ok(PlyScalar::Double),
+            _ => Err(SlicerErrors::PlyLoadError),
+        }
+    }
+
+    fn size(&self) -> usize {
+        match self {
+            PlyScalar::Char | PlyScalar::UChar => 1,
+            PlyScalar::Short | PlyScalar::UShort => 2,
+            PlyScalar::Int | PlyScalar::UInt | PlyScalar::Float => 4,
+            PlyScalar::Double => 8,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum PlyProperty {
+    Scalar {
+        name: String,
+        scalar_type: PlyScalar,
+    },
+    List {
+        name: String,
+        count_type: PlyScalar,
+        item_type: PlyScalar,
+    },
+}
+
+#[derive(Debug)]
+struct PlyElement {
+    name: String,
+    count: usize,
+    properties: Vec<PlyProperty>,
+}
+
+///Reads values from the body of the file in the format declared by the header
+struct PlyValueReader<R: BufRead> {
+    reader: R,
+    format: PlyFormat,
+    tokens: VecDeque<String>,
+}
+
+impl<R: BufRead> PlyValueReader<R> {
+    fn read_value(&mut self, scalar_type: PlyScalar) -> Result<f64, SlicerErrors> {
+        match self.format {
+            PlyFormat::Ascii => {
+                while self.tokens.is_empty() {
+                    let mut line = String::new();
+                    if self
+                        .reader
+                        .read_line(&mut line)
+                        .map_err(|_| SlicerErrors::PlyLoadError)?
+                        == 0
+                    {
+                        return Err(SlicerErrors::PlyLoadError);
+                    }
+                    self.tokens
+                        .extend(line.split_whitespace().map(|str| str.to_string()));
+                }
+
+                self.tokens
+                    .pop_front()
+                    .and_then(|str| str.parse().ok())
+                    .ok_or(SlicerErrors::PlyLoadError)
+            }
+            PlyFormat::BinaryLittleEndian | PlyFormat::BinaryBigEndian => {
+                let mut buf = [0u8; 8];
+                let size = scalar_type.size();
+                self.reader
+                    .read_exact(&mut buf[..size])
+                    .map_err(|_| SlicerErrors::PlyLoadError)?;
+
+                //Convert everything to little endian
+                if self.format == PlyFormat::BinaryBigEndian {
+                    buf[..size].reverse();
+                }
+
+                Ok(match scalar_type {
+                    PlyScalar::Char => i8::from_le_bytes([buf[0]]) as f64,
+                    PlyScalar::UChar => buf[0] as f64,
+                    PlyScalar::Short => i16::from_le_bytes([buf[0], buf[1]]) as f64,
+                    PlyScalar::UShort => u16::from_le_bytes([buf[0], buf[1]]) as f64,
+                    PlyScalar::Int => i32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as f64,
+                    PlyScalar::UInt => u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as f64,
+                    PlyScalar::Float => f32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as f64,
+                    PlyScalar::Double => f64::from_le_bytes(buf),
+                })
+            }
+        }
+    }
+}
+
+fn parse_ply<R: BufRead>(mut reader: R) -> Result<Vec<LoadedModel>, SlicerErrors> {
+    let (format, elements) = parse_header(&mut reader)?;
+
+    let mut value_reader = PlyValueReader {
+        reader,
+        format,
+        tokens: VecDeque::new(),
+    };
+
+    let mut vertices = vec![];
+    let mut triangles = vec![];
+
+    for element in &elements {
+        for _ in 0..element.count {
+            let mut position = [0.0; 3];
+            let mut face = vec![];
+
+            for property in &element.properties {
+                match property {
+                    PlyProperty::Scalar { name, scalar_type } => {
+                        let value = value_reader.read_value(*scalar_type)?;
+                        match name.as_str() {
+                            "x" => position[0] = value,
+                            "y" => position[1] = value,
+                            "z" => position[2] = value,
+                            _ => {}
+                        }
+                    }
+                    PlyProperty::List {
+                        name,
+                        count_type,
+                        item_type,
+                    } => {
+                        let count = value_reader.read_value(*count_type)? as usize;
+                        let is_face_indices = name == "vertex_indices" || name == "vertex_index";
+                        for _ in 0..count {
+                            let value = value_reader.read_value(*item_type)?;
+                            if is_face_indices {
+                                face.push(value as usize);
+                            }
+                        }
+                    }
+                }
+            }
+
+            match element.name.as_str() {
+                "vertex" => vertices.push(Vertex {
+                    x: position[0],
+                    y: position[1],
+                    z: position[2],
+                }),
+                "face" => {
+                    if face.len() < 3 {
+                        return Err(SlicerErrors::PlyLoadError);
+                    }
+
+                    //Fan triangulation of the polygon
+                    for (second, third) in face[1..].iter().zip(face[2..].iter()) {
+                        triangles.push(IndexedTriangle {
+                            verts: [face[0], *second, *third],
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    if triangles.is_empty()
+        || triangles
+            .iter()
+            .flat_map(|tri| tri.verts.iter())
+            .any(|index| *index >= vertices.len())
+    {
+        return Err(SlicerErrors::PlyLoadError);
+    }
+
+    let triangles = triangles
+        .into_iter()
+        .map(|tri| rotate_lowest_vertex_first(tri, &vertices))
+        .collect();
+
+    Ok(vec![(vertices, triangles)])
+}
+
+fn parse_header<R: BufRead>(reader: &mut R) -> Result<(PlyFormat, Vec<PlyElement>), SlicerErrors> {
+    let mut format = None;
+    let mut elements: Vec<PlyElement> = vec![];
+    let mut first_line = true;
+
+    loop {
+        let mut line = String::new();
+        if reader
+            .read_line(&mut line)
+            .map_err(|_| SlicerErrors::PlyLoadError)?
+            == 0
+        {
+            return Err(SlicerErrors::PlyLoadError);
+        }
+
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+
+        if first_line {
+            if tokens != ["ply"] {
+                return Err(SlicerErrors::PlyLoadError);
+            }
+            first_line = false;
+            continue;
+        }
+
+        match tokens.as_slice() {
+            ["format", format_name, _version] => {
+                format = Some(match *format_name {
+                    "ascii" => PlyFormat::Ascii,
+                    "binary_little_endian" => PlyFormat::BinaryLittleEndian,
+                    "binary_big_endian" => PlyFormat::BinaryBigEndian,
+                    _ => return Err(SlicerErrors::PlyLoadError),
+                });
+            }
+            ["element", name, count] => {
+                elements.push(PlyElement {
+                    name: name.to_string(),
+                    count: count.parse().map_err(|_| SlicerErrors::PlyLoadError)?,
+                    properties: vec![],
+                });
+            }
+            ["property", "list", count_type, item_type, name] => {
+                elements
+                    .last_mut()
+                    .ok_or(SlicerErrors::PlyLoadError)?
+                    .properties
+                    .push(PlyProperty::List {
+                        name: name.to_string(),
+                        count_type: PlyScalar::from_name(count_type)?,
+                        item_type: PlyScalar::from_name(item_type)?,
+                    });
+            }
+            ["property", scalar_type, name] => {
+                elements
+                    .last_mut()
+                    .ok_or(SlicerErrors::PlyLoadError)?
+                    .properties
+                    .push(PlyProperty::Scalar {
+                        name: name.to_string(),
+                        scalar_type: PlyScalar::from_name(scalar_type)?,
+                    });
+            }
+            ["end_header"] => {
+                return Ok((format.ok_or(SlicerErrors::PlyLoadError)?, elements));
+            }
+            //comment and obj_info lines
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ascii_quad_is_triangulated() {
+        let ply = "ply\nformat ascii 1.0\ncomment test\n\
+                   element vertex 4\nproperty float x\nproperty float y\nproperty float z\n\
+                   element face 1\nproperty list uchar int vertex_indices\nend_header\n\
+                   0 0 0\n1 0 0\n1 1 0\n0 1 0\n4 0 1 2 3\n";
+        let models = parse_ply(ply.as_bytes()).unwrap();
+
+        assert_eq!(models.len(), 1);
+        assert_eq!(models[0].0.len(), 4);
+        assert_eq!(models[0].1.len(), 2);
+    }
+
+    #[test]
+    fn binary_endianness() {
+        for (format, big_endian) in [("binary_little_endian", false), ("binary_big_endian", true)] {
+            let mut ply = format!(
+                "ply\nformat {} 1.0\n\
+                 element vertex 3\nproperty double x\nproperty double y\nproperty double z\n\
+                 element face 1\nproperty list uchar uint vertex_indices\nend_header\n",
+                format
+            )
+            .into_bytes();
+
+            for value in [0.0f64, 0.0, 0.0, 2.0, 0.0, 0.0, 0.0, 3.0, 1.0] {
+                if big_endian {
+                    ply.extend(value.to_be_bytes());
+                } else {
+                    ply.extend(value.to_le_bytes());
+                }
+            }
+            ply.push(3);
+            for index in [0u32, 1, 2] {
+                if big_endian {
+                    ply.extend(index.to_be_bytes());
+                } else {
+                    ply.extend(index.to_le_bytes());
+                }
+            }
+
+            let models = parse_ply(ply.as_slice()).unwrap();
+
+            assert_eq!(
+                models[0].0[2],
+                Vertex {
+                    x: 0.0,
+                    y: 3.0,
+                    z: 1.0
+                }
+            );
+            assert_eq!(models[0].1[0].verts, [0, 1, 2]);
+        }
+    }
+}
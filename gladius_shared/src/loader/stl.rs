@@ -1,14 +1,30 @@
 use crate::loader::*;
-use std::io::BufReader;
+use std::io::{BufReader, Cursor, Read, Seek};
 
 ///Loader for STL files
 pub struct STLLoader {}
 
 impl Loader for STLLoader {
-    fn load(
-        &self,
-        filepath: &str,
-    ) -> Result<Vec<(Vec<Vertex>, Vec<IndexedTriangle>)>, SlicerErrors> {
+    fn extensions(&self) -> &'static [&'static str] {
+        &["stl"]
+    }
+
+    fn detect(&self, header: &[u8], file_size: u64) -> bool {
+        //Binary files have an 80 byte header then the triangle count and 50 bytes per triangle
+        let binary = header.len() >= 84 && {
+            let count = u32::from_le_bytes([header[80], header[81], header[82], header[83]]);
+            84 + 50 * count as u64 == file_size
+        };
+
+        let text_start = header
+            .iter()
+            .position(|byte| !byte.is_ascii_whitespace())
+            .unwrap_or(header.len());
+
+        binary || header[text_start..].starts_with(b"solid")
+    }
+
+    fn load(&self, filepath: &str) -> Result<Vec<LoadedModel>, SlicerErrors> {
         let file = std::fs::OpenOptions::new()
             .read(true)
             .open(filepath)
@@ -16,63 +32,41 @@ impl Loader for STLLoader {
                 filepath: filepath.to_string(),
             })?;
 
-        let mut root_vase = BufReader::new(&file);
-        let mesh: nom_stl::IndexMesh = nom_stl::parse_stl(&mut root_vase)
-            .map_err(|_| SlicerErrors::StlLoadError)?
-            .into();
+        parse_stl(&mut BufReader::new(&file))
+    }
 
-        let mut triangles = vec![];
-        let vertices = mesh
-            .vertices()
-            .iter()
-            .map(|vert| Vertex {
-                x: vert[0] as f64,
-                y: vert[1] as f64,
-                z: vert[2] as f64,
-            })
-            .collect::<Vec<Vertex>>();
+    fn load_from_bytes(&self, contents: &[u8]) -> Result<Vec<LoadedModel>, SlicerErrors> {
+        parse_stl(&mut Cursor::new(contents))
+    }
+}
 
-        for triangle in mesh.triangles() {
-            let mut converted_tri = IndexedTriangle {
-                verts: [
-                    triangle.vertices_indices()[0],
-                    triangle.vertices_indices()[1],
-                    triangle.vertices_indices()[2],
-                ],
-            };
-            /*
-                        let A = v1.x * v0.y + v2.x * v1.y + v0.x * v2.y;
-                        let B = v0.x * v1.y + v1.x * v2.y + v2.x * v0.y;
+fn parse_stl<R: Read + Seek>(reader: &mut R) -> Result<Vec<LoadedModel>, SlicerErrors> {
+    let mesh: nom_stl::IndexMesh = nom_stl::parse_stl(reader)
+        .map_err(|_| SlicerErrors::StlLoadError)?
+        .into();
 
-                        if  A < B
-                        {
-                            let temp = converted_tri.verts[0];
-                            converted_tri.verts[0] = converted_tri.verts[1];
-                            converted_tri.verts[1] = temp;
-                            std::mem::swap(&mut v0, &mut v1);
-                        }
-            */
-            let v0 = vertices[converted_tri.verts[0]];
-            let v1 = vertices[converted_tri.verts[1]];
-            let v2 = vertices[converted_tri.verts[2]];
+    let mut triangles = vec![];
+    let vertices = mesh
+        .vertices()
+        .iter()
+        .map(|vert| Vertex {
+            x: vert[0] as f64,
+            y: vert[1] as f64,
+            z: vert[2] as f64,
+        })
+        .collect::<Vec<Vertex>>();
 
-            if v0 < v1 && v0 < v2 {
-                triangles.push(converted_tri);
-            } else if v1 < v2 && v1 < v0 {
-                let temp = converted_tri.verts[0];
-                converted_tri.verts[0] = converted_tri.verts[1];
-                converted_tri.verts[1] = converted_tri.verts[2];
-                converted_tri.verts[2] = temp;
-                triangles.push(converted_tri);
-            } else {
-                let temp = converted_tri.verts[0];
-                converted_tri.verts[0] = converted_tri.verts[2];
-                converted_tri.verts[2] = converted_tri.verts[1];
-                converted_tri.verts[1] = temp;
-                triangles.push(converted_tri);
-            }
-        }
+    for triangle in mesh.triangles() {
+        let converted_tri = IndexedTriangle {
+            verts: [
+                triangle.vertices_indices()[0],
+                triangle.vertices_indices()[1],
+                triangle.vertices_indices()[2],
+            ],
+        };
 
-        Ok(vec![(vertices, triangles)])
+        triangles.push(rotate_lowest_vertex_first(converted_tri, &vertices));
     }
+
+    Ok(vec![(vertices, triangles)])
 }
@@ -1,6 +1,11 @@
 use crate::error::SlicerErrors;
 use crate::loader::*;
 use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Cursor, Read, Seek};
+use zip::ZipArchive;
 
 #[derive(Deserialize, Debug)]
 struct Relationships {
@@ -21,6 +26,7 @@ struct Relationship {
 #[derive(Deserialize, Debug)]
 #[serde(rename = "model")]
 struct ThreeMFModel {
+    unit: Option<String>,
     resources: ThreeMFResource,
     build: ThreeMFBuild,
 }
@@ -39,12 +45,30 @@ struct ThreeMFObject {
     mesh: Option<ThreeMFMesh>,
     components: Option<ThreeMFComponents>,
     id: usize,
+    name: Option<String>,
+    partnumber: Option<String>,
+    metadatagroup: Option<ThreeMFMetadataGroup>,
 }
 
 #[derive(Deserialize, Debug)]
 struct ThreeMFItem {
     objectid: usize,
     transform: Option<String>,
+    partnumber: Option<String>,
+    metadatagroup: Option<ThreeMFMetadataGroup>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ThreeMFMetadataGroup {
+    #[serde(rename = "metadata", default)]
+    list: Vec<ThreeMFMetadata>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ThreeMFMetadata {
+    name: String,
+    #[serde(rename = "$value", default)]
+    value: String,
 }
 
 #[derive(Deserialize, Debug)]
@@ -88,69 +112,351 @@ struct ThreeMFTriangles {
 pub struct ThreeMFLoader {}
 
 impl Loader for ThreeMFLoader {
-    fn load(
+    fn extensions(&self) -> &'static [&'static str] {
+        &["3mf"]
+    }
+
+    fn detect(&self, header: &[u8], _file_size: u64) -> bool {
+        //Other zip files such as zipped AMF files share the zip signature
+        zip_entry_names(header)
+            .iter()
+            .any(|name| *name == b"3D/3dmodel.model" || *name == b"_rels/.rels")
+    }
+
+    fn load(&self, filepath: &str) -> Result<Vec<LoadedModel>, SlicerErrors> {
+        Ok(self
+            .load_with_metadata(filepath)?
+            .into_iter()
+            .map(|(_metadata, vertices, triangles)| (vertices, triangles))
+            .collect())
+    }
+
+    fn load_from_bytes(&self, contents: &[u8]) -> Result<Vec<LoadedModel>, SlicerErrors> {
+        Ok(self
+            .load_from_bytes_with_metadata(contents)?
+            .into_iter()
+            .map(|(_metadata, vertices, triangles)| (vertices, triangles))
+            .collect())
+    }
+
+    fn load_from_bytes_with_metadata(
+        &self,
+        contents: &[u8],
+    ) -> Result<Vec<LoadedModelWithMetadata>, SlicerErrors> {
+        load_archive(&mut open_archive_from_bytes(contents)?)
+    }
+
+    fn load_with_metadata(
         &self,
         filepath: &str,
-    ) -> Result<Vec<(Vec<Vertex>, Vec<IndexedTriangle>)>, SlicerErrors> {
-        let zipfile = std::fs::File::open(filepath).unwrap();
+    ) -> Result<Vec<LoadedModelWithMetadata>, SlicerErrors> {
+        load_archive(&mut open_archive(filepath)?)
+    }
 
-        let mut archive =
-            zip::ZipArchive::new(zipfile).map_err(|_| SlicerErrors::ThreemfUnsupportedType)?;
+    fn load_settings(&self, filepath: &str) -> Result<Option<PartialSettings>, SlicerErrors> {
+        load_archive_settings(&mut open_archive(filepath)?)
+    }
 
-        let rel_file = match archive.by_name("_rels/.rels") {
-            Ok(file) => file,
-            Err(..) => {
-                return Err(SlicerErrors::ThreemfLoadError);
-            }
-        };
+    fn load_settings_from_bytes(
+        &self,
+        contents: &[u8],
+    ) -> Result<Option<PartialSettings>, SlicerErrors> {
+        load_archive_settings(&mut open_archive_from_bytes(contents)?)
+    }
+}
 
-        let rel: Relationships = serde_xml_rs::de::from_reader(rel_file).unwrap();
+fn load_archive_settings<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+) -> Result<Option<PartialSettings>, SlicerErrors> {
+    let config_files: Vec<String> = archive
+        .file_names()
+        .filter(|name| name.starts_with("Metadata/") && name.ends_with(".config"))
+        .map(|name| name.to_string())
+        .collect();
+
+    let mut values = HashMap::new();
+
+    for config_file in config_files {
+        let mut contents = String::new();
+        archive
+            .by_name(&config_file)
+            .map_err(|_| SlicerErrors::ThreemfLoadError)?
+            .read_to_string(&mut contents)
+            .map_err(|_| SlicerErrors::ThreemfLoadError)?;
+
+        read_config_values(&contents, &mut values);
+    }
 
-        let model_path = rel.relationship[0].target.clone();
+    if values.is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(convert_config_values(&values)))
+    }
+}
 
-        let model_file = match archive.by_name(&model_path[1..]) {
-            Ok(file) => file,
-            Err(..) => {
-                return Err(SlicerErrors::ThreemfLoadError);
-            }
-        };
+fn load_archive<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+) -> Result<Vec<LoadedModelWithMetadata>, SlicerErrors> {
+    let rel_file = match archive.by_name("_rels/.rels") {
+        Ok(file) => file,
+        Err(..) => {
+            return Err(SlicerErrors::ThreemfLoadError);
+        }
+    };
+
+    let rel: Relationships =
+        serde_xml_rs::de::from_reader(rel_file).map_err(|_| SlicerErrors::ThreemfLoadError)?;
+
+    let model_path = rel
+        .relationship
+        .first()
+        .ok_or(SlicerErrors::ThreemfLoadError)?
+        .target
+        .clone();
+
+    let model_file = match archive.by_name(model_path.trim_start_matches('/')) {
+        Ok(file) => file,
+        Err(..) => {
+            return Err(SlicerErrors::ThreemfLoadError);
+        }
+    };
 
-        let model: ThreeMFModel = serde_xml_rs::de::from_reader(model_file).unwrap();
+    let model: ThreeMFModel =
+        serde_xml_rs::de::from_reader(model_file).map_err(|_| SlicerErrors::ThreemfLoadError)?;
 
-        model
-            .build
-            .item
-            .iter()
-            .map(|item| {
-                let (mut v, t) = handle_object(item.objectid, &model.resources)?;
+    //Transforms are in model units so scale after they are applied
+    let scale = match &model.unit {
+        Some(unit) => unit_to_millimeter_scale(unit).ok_or(SlicerErrors::ThreemfLoadError)?,
+        None => 1.0,
+    };
+
+    model
+        .build
+        .item
+        .iter()
+        .map(|item| {
+            let (mut v, t) = handle_object(item.objectid, &model.resources, 0)?;
 
-                if let Some(t_str) = &item.transform {
-                    let transform = get_transform_from_string(t_str)?;
+            if let Some(t_str) = &item.transform {
+                let transform = get_transform_from_string(t_str)?;
 
-                    for vert in v.iter_mut() {
-                        *vert = &transform * *vert;
-                    }
+                for vert in v.iter_mut() {
+                    *vert = &transform * *vert;
                 }
-                Ok((v, t))
-            })
-            .collect()
+            }
+
+            for vert in v.iter_mut() {
+                vert.x *= scale;
+                vert.y *= scale;
+                vert.z *= scale;
+            }
+
+            let object = model
+                .resources
+                .object
+                .iter()
+                .find(|obj| obj.id == item.objectid);
+
+            Ok((get_metadata(item, object), v, t))
+        })
+        .collect()
+}
+
+///Get the name of the item's object and the metadata of the item followed by the metadata of the object
+fn get_metadata(item: &ThreeMFItem, object: Option<&ThreeMFObject>) -> ModelMetadata {
+    let mut values = vec![];
+
+    let mut add_values = |partnumber: &Option<String>, group: &Option<ThreeMFMetadataGroup>| {
+        if let Some(partnumber) = partnumber {
+            values.push(("partnumber".to_string(), partnumber.clone()));
+        }
+        for metadata in group.iter().flat_map(|group| group.list.iter()) {
+            values.push((metadata.name.clone(), metadata.value.clone()));
+        }
+    };
+
+    add_values(&item.partnumber, &item.metadatagroup);
+    if let Some(object) = object {
+        add_values(&object.partnumber, &object.metadatagroup);
+    }
+
+    ModelMetadata {
+        name: object.and_then(|obj| obj.name.clone()),
+        values,
+    }
+}
+
+fn open_archive(filepath: &str) -> Result<ZipArchive<File>, SlicerErrors> {
+    let zipfile = File::open(filepath).map_err(|_| SlicerErrors::ObjectFileNotFound {
+        filepath: filepath.to_string(),
+    })?;
+
+    ZipArchive::new(zipfile).map_err(|_| SlicerErrors::ThreemfUnsupportedType)
+}
+
+fn open_archive_from_bytes(contents: &[u8]) -> Result<ZipArchive<Cursor<&[u8]>>, SlicerErrors> {
+    ZipArchive::new(Cursor::new(contents)).map_err(|_| SlicerErrors::ThreemfUnsupportedType)
+}
+
+///Read the key value pairs from a PrusaSlicer style ini config or an Orca/Bambu style json config.
+/// Other config files such as the per object xml configs are ignored.
+fn read_config_values(contents: &str, values: &mut HashMap<String, String>) {
+    let contents = contents.trim_start();
+
+    if contents.starts_with('{') {
+        if let Ok(Value::Object(map)) = serde_json::from_str(contents) {
+            for (key, value) in map {
+                let value = match value {
+                    Value::String(str) => str,
+                    Value::Number(num) => num.to_string(),
+                    Value::Bool(b) => b.to_string(),
+                    //Multi extruder values are stored as a list, treat them like the ini comma lists
+                    Value::Array(list) => list
+                        .iter()
+                        .filter_map(|value| value.as_str())
+                        .collect::<Vec<&str>>()
+                        .join(","),
+                    _ => continue,
+                };
+                values.entry(key).or_insert(value);
+            }
+        }
+    } else if !contents.starts_with('<') {
+        for line in contents.lines() {
+            //PrusaSlicer prefixes every line with a comment
+            let line = line.trim_start_matches(';').trim();
+
+            if let Some((key, value)) = line.split_once('=') {
+                values
+                    .entry(key.trim().to_string())
+                    .or_insert_with(|| value.trim().to_string());
+            }
+        }
+    }
+}
+
+///Map the config values that have an equivalent setting onto partial settings
+fn convert_config_values(values: &HashMap<String, String>) -> PartialSettings {
+    let get = |keys: &[&str]| keys.iter().find_map(|key| values.get(*key));
+
+    //Multi extruder settings are comma separated so only the first value is used
+    let number = |keys: &[&str]| {
+        get(keys)
+            .and_then(|value| value.split(',').next())
+            .and_then(|value| value.trim().parse::<f64>().ok())
+    };
+    let count = |keys: &[&str]| number(keys).map(|value| value as usize);
+    let percentage = |keys: &[&str]| {
+        get(keys)
+            .and_then(|value| value.trim().strip_suffix('%'))
+            .and_then(|value| value.trim().parse::<f64>().ok())
+            .map(|value| value / 100.0)
+    };
+    let boolean = |keys: &[&str]| {
+        get(keys).and_then(|value| match value.trim() {
+            "1" | "true" => Some(true),
+            "0" | "false" => Some(false),
+            _ => None,
+        })
+    };
+
+    let bed_size = get(&["bed_shape", "printable_area"]).and_then(|shape| get_bed_size(shape));
+
+    let inner_perimeters_first = boolean(&["external_perimeters_first"])
+        .map(|outer_first| !outer_first)
+        .or_else(|| {
+            get(&["wall_sequence"]).map(|sequence| sequence.starts_with("inner wall/outer wall"))
+        });
+
+    PartialSettings {
+        layer_height: number(&["layer_height"]),
+        //Zero is automatic width
+        layer_width: number(&["extrusion_width", "line_width"]).filter(|width| *width > 0.0),
+        nozzle_diameter: number(&["nozzle_diameter"]),
+        retract_length: number(&["retract_length", "retraction_length"]),
+        retract_lift_z: number(&["retract_lift", "z_hop"]),
+        retract_speed: number(&["retract_speed", "retraction_speed"]),
+        infill_percentage: percentage(&["fill_density", "sparse_infill_density"]),
+        infill_perimeter_overlap_percentage: percentage(&["infill_overlap", "infill_wall_overlap"]),
+        inner_perimeters_first,
+        number_of_perimeters: count(&["perimeters", "wall_loops"]),
+        top_layers: count(&["top_solid_layers", "top_shell_layers"]),
+        bottom_layers: count(&["bottom_solid_layers", "bottom_shell_layers"]),
+        print_x: bed_size.map(|(x, _)| x),
+        print_y: bed_size.map(|(_, y)| y),
+        print_z: number(&["max_print_height", "printable_height"]),
+        brim_width: number(&["brim_width"]).filter(|width| *width > 0.0),
+        arrange_spacing: number(&["min_object_distance", "duplicate_distance"]),
+        partial_infill_type: get(&["fill_pattern", "sparse_infill_pattern"])
+            .and_then(|pattern| convert_infill_pattern(pattern)),
+        ..Default::default()
+    }
+}
+
+///Get the size of the bed from a list of its corners such as "0x0,250x0,250x210,0x210"
+fn get_bed_size(shape: &str) -> Option<(f64, f64)> {
+    let corners: Vec<(f64, f64)> = shape
+        .split(',')
+        .map(|corner| {
+            let (x, y) = corner.trim().split_once('x')?;
+            Some((x.parse().ok()?, y.parse().ok()?))
+        })
+        .collect::<Option<_>>()?;
+
+    let (min_x, max_x, min_y, max_y) = corners.iter().fold(
+        (
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+        ),
+        |a, b| (a.0.min(b.0), a.1.max(b.0), a.2.min(b.1), a.3.max(b.1)),
+    );
+
+    if max_x > min_x && max_y > min_y {
+        Some((max_x - min_x, max_y - min_y))
+    } else {
+        None
+    }
+}
+
+fn convert_infill_pattern(pattern: &str) -> Option<PartialInfillTypes> {
+    match pattern.trim() {
+        "rectilinear" | "alignedrectilinear" | "line" | "zig-zag" => {
+            Some(PartialInfillTypes::Linear)
+        }
+        "grid" => Some(PartialInfillTypes::Rectilinear),
+        "triangles" => Some(PartialInfillTypes::Triangle),
+        "cubic" => Some(PartialInfillTypes::Cubic),
+        "lightning" => Some(PartialInfillTypes::Lightning),
+        _ => None,
     }
 }
 
 fn handle_object(
     obj_index: usize,
     comps: &ThreeMFResource,
-) -> Result<(Vec<Vertex>, Vec<IndexedTriangle>), SlicerErrors> {
-    let object = comps.object.iter().find(|obj| obj.id == obj_index).unwrap();
+    depth: usize,
+) -> Result<LoadedModel, SlicerErrors> {
+    //Protect against components that contain themselves
+    if depth > comps.object.len() {
+        return Err(SlicerErrors::ThreemfLoadError);
+    }
+
+    let object = comps
+        .object
+        .iter()
+        .find(|obj| obj.id == obj_index)
+        .ok_or(SlicerErrors::ThreemfLoadError)?;
 
     if let Some(mesh) = &object.mesh {
-        Ok(handle_mesh(mesh))
+        handle_mesh(mesh)
     } else if let Some(components) = &object.components {
         let mut v = vec![];
         let mut t = vec![];
         let mut start = 0;
         for component in &components.component {
-            let (mut verts, mut triangles) = handle_object(component.objectid, comps)?;
+            let (mut verts, mut triangles) = handle_object(component.objectid, comps, depth + 1)?;
 
             if let Some(t_str) = &component.transform {
                 let transform = get_transform_from_string(t_str)?;
@@ -180,36 +486,27 @@ fn handle_object(
     }
 }
 
-fn handle_mesh(mesh: &ThreeMFMesh) -> (Vec<Vertex>, Vec<IndexedTriangle>) {
+fn handle_mesh(mesh: &ThreeMFMesh) -> Result<LoadedModel, SlicerErrors> {
     let mut triangles = vec![];
     let vertices = mesh.vertices.list.clone();
 
     for triangle in &mesh.triangles.list {
-        let mut converted_tri = IndexedTriangle {
+        let converted_tri = IndexedTriangle {
             verts: [triangle.v1, triangle.v2, triangle.v3],
         };
-        let v0 = vertices[converted_tri.verts[0]];
-        let v1 = vertices[converted_tri.verts[1]];
-        let v2 = vertices[converted_tri.verts[2]];
-
-        if v0 < v1 && v0 < v2 {
-            triangles.push(converted_tri);
-        } else if v1 < v2 && v1 < v0 {
-            let temp = converted_tri.verts[0];
-            converted_tri.verts[0] = converted_tri.verts[1];
-            converted_tri.verts[1] = converted_tri.verts[2];
-            converted_tri.verts[2] = temp;
-            triangles.push(converted_tri);
-        } else {
-            let temp = converted_tri.verts[0];
-            converted_tri.verts[0] = converted_tri.verts[2];
-            converted_tri.verts[2] = converted_tri.verts[1];
-            converted_tri.verts[1] = temp;
-            triangles.push(converted_tri);
+
+        if converted_tri
+            .verts
+            .iter()
+            .any(|index| *index >= vertices.len())
+        {
+            return Err(SlicerErrors::ThreemfLoadError);
         }
+
+        triangles.push(rotate_lowest_vertex_first(converted_tri, &vertices));
     }
 
-    (vertices, triangles)
+    Ok((vertices, triangles))
 }
 
 fn get_transform_from_string(transform_string: &str) -> Result<Transform, SlicerErrors> {
@@ -229,3 +526,190 @@ fn get_transform_from_string(transform_string: &str) -> Result<Transform, Slicer
         Ok(Transform(t))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::write::FileOptions;
+    use zip::ZipWriter;
+
+    const MODEL: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<model unit="centimeter" xmlns="http://schemas.microsoft.com/3dmanufacturing/core/2015/02">
+  <resources>
+    <object id="1" name="Bracket" partnumber="B-100" type="model">
+      <metadatagroup>
+        <metadata name="Material">PLA</metadata>
+      </metadatagroup>
+      <mesh>
+        <vertices>
+          <vertex x="0" y="0" z="0"/>
+          <vertex x="1" y="0" z="0"/>
+          <vertex x="0" y="1" z="0"/>
+          <vertex x="0" y="0" z="1"/>
+        </vertices>
+        <triangles>
+          <triangle v1="0" v2="2" v3="1"/>
+          <triangle v1="0" v2="1" v3="3"/>
+          <triangle v1="0" v2="3" v3="2"/>
+          <triangle v1="1" v2="2" v3="3"/>
+        </triangles>
+      </mesh>
+    </object>
+  </resources>
+  <build>
+    <item objectid="1" transform="1 0 0 0 1 0 0 0 1 2 0 0" partnumber="Item-1"/>
+  </build>
+</model>"#;
+
+    const RELS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">
+  <Relationship Target="/3D/3dmodel.model" Id="rel0" Type="http://schemas.microsoft.com/3dmanufacturing/2013/01/3dmodel"/>
+</Relationships>"#;
+
+    ///Zip the files into an archive held in memory
+    fn archive(files: &[(&str, &str)]) -> Vec<u8> {
+        let mut writer = ZipWriter::new(Cursor::new(vec![]));
+        for (name, contents) in files {
+            writer.start_file(*name, FileOptions::default()).unwrap();
+            writer.write_all(contents.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn units_names_and_metadata() {
+        let contents = archive(&[("_rels/.rels", RELS), ("3D/3dmodel.model", MODEL)]);
+        let mut models = load_archive(&mut open_archive_from_bytes(&contents).unwrap()).unwrap();
+
+        assert_eq!(models.len(), 1);
+        let (metadata, vertices, triangles) = models.remove(0);
+
+        assert_eq!(metadata.name.as_deref(), Some("Bracket"));
+        assert_eq!(
+            metadata.values,
+            vec![
+                ("partnumber".to_string(), "Item-1".to_string()),
+                ("partnumber".to_string(), "B-100".to_string()),
+                ("Material".to_string(), "PLA".to_string()),
+            ]
+        );
+
+        //The item transform is in centimeters and is applied before scaling
+        assert_eq!(triangles.len(), 4);
+        assert_eq!(
+            (vertices[0].x, vertices[0].y, vertices[0].z),
+            (20.0, 0.0, 0.0)
+        );
+        assert_eq!(
+            (vertices[1].x, vertices[1].y, vertices[1].z),
+            (30.0, 0.0, 0.0)
+        );
+        assert_eq!(
+            (vertices[3].x, vertices[3].y, vertices[3].z),
+            (20.0, 0.0, 10.0)
+        );
+    }
+
+    #[test]
+    fn names_and_settings_load_from_bytes() {
+        let contents = archive(&[
+            ("_rels/.rels", RELS),
+            ("3D/3dmodel.model", MODEL),
+            ("Metadata/Slic3r_PE.config", "; perimeters = 4\n"),
+        ]);
+        let loader = ThreeMFLoader {};
+
+        let models = loader.load_from_bytes_with_metadata(&contents).unwrap();
+        assert_eq!(models.len(), 1);
+        assert_eq!(models[0].0.name.as_deref(), Some("Bracket"));
+
+        let settings = loader.load_settings_from_bytes(&contents).unwrap().unwrap();
+        assert_eq!(settings.number_of_perimeters, Some(4));
+    }
+
+    #[test]
+    fn malformed_archives_return_errors() {
+        let loader = ThreeMFLoader {};
+        let model_with = |from: &str, to: &str| MODEL.replace(from, to);
+
+        let dangling_object = model_with(r#"item objectid="1""#, r#"item objectid="2""#);
+        let missing_vertex = model_with(r#"v1="1" v2="2" v3="3""#, r#"v1="1" v2="2" v3="4""#);
+        let recursive_component = model_with(
+            "  </resources>",
+            r#"<object id="2"><components><component objectid="2"/></components></object></resources>"#,
+        )
+        .replace(r#"item objectid="1""#, r#"item objectid="2""#);
+
+        for files in [
+            vec![("3D/3dmodel.model", MODEL)],
+            vec![
+                ("_rels/.rels", "<Relationships"),
+                ("3D/3dmodel.model", MODEL),
+            ],
+            vec![("_rels/.rels", RELS)],
+            vec![("_rels/.rels", RELS), ("3D/3dmodel.model", "<model>")],
+            vec![
+                ("_rels/.rels", RELS),
+                ("3D/3dmodel.model", &dangling_object),
+            ],
+            vec![("_rels/.rels", RELS), ("3D/3dmodel.model", &missing_vertex)],
+            vec![
+                ("_rels/.rels", RELS),
+                ("3D/3dmodel.model", &recursive_component),
+            ],
+        ] {
+            assert!(matches!(
+                loader.load_from_bytes(&archive(&files)),
+                Err(SlicerErrors::ThreemfLoadError)
+            ));
+        }
+
+        assert!(matches!(
+            loader.load_from_bytes(b"PK\x03\x04 truncated"),
+            Err(SlicerErrors::ThreemfUnsupportedType)
+        ));
+    }
+
+    #[test]
+    fn prusa_config_values() {
+        let config = "; generated by PrusaSlicer 2.4.0\n\
+                      ; bed_shape = 0x0,250x0,250x210,0x210\n\
+                      ; external_perimeters_first = 0\n\
+                      ; fill_density = 15%\n\
+                      ; fill_pattern = grid\n\
+                      ; layer_height = 0.1\n\
+                      ; nozzle_diameter = 0.6,0.4\n\
+                      ; perimeters = 4\n";
+        let mut values = HashMap::new();
+        read_config_values(config, &mut values);
+        let settings = convert_config_values(&values);
+
+        assert_eq!(settings.layer_height, Some(0.1));
+        assert_eq!(settings.nozzle_diameter, Some(0.6));
+        assert_eq!(settings.number_of_perimeters, Some(4));
+        assert_eq!(settings.infill_percentage, Some(0.15));
+        assert_eq!(settings.inner_perimeters_first, Some(true));
+        assert_eq!(settings.print_x, Some(250.0));
+        assert_eq!(settings.print_y, Some(210.0));
+        assert_eq!(
+            settings.partial_infill_type,
+            Some(PartialInfillTypes::Rectilinear)
+        );
+        assert_eq!(settings.top_layers, None);
+    }
+
+    #[test]
+    fn orca_config_values() {
+        let config = "{\"layer_height\": \"0.28\", \"wall_loops\": \"2\", \
+                      \"nozzle_diameter\": [\"0.4\"], \"sparse_infill_density\": \"20%\"}";
+        let mut values = HashMap::new();
+        read_config_values(config, &mut values);
+        let settings = convert_config_values(&values);
+
+        assert_eq!(settings.layer_height, Some(0.28));
+        assert_eq!(settings.number_of_perimeters, Some(2));
+        assert_eq!(settings.nozzle_diameter, Some(0.4));
+        assert_eq!(settings.infill_percentage, Some(0.2));
+    }
+}
@@ -1,7 +1,7 @@
 #![deny(missing_docs)]
 
 use crate::error::SlicerErrors;
-use crate::types::{CalculatedValues, Command};
+use crate::types::{CalculatedValues, Command, ModelCheckReport, ObjectDescription};
 use serde::{Deserialize, Serialize};
 
 ///Messages for communicating between the slicer and another process
@@ -21,4 +21,10 @@ pub enum Message {
 
     ///Message to share any Errors encountered
     Error(SlicerErrors),
+
+    ///Message to share the results of checking the models
+    CheckReport(Vec<ModelCheckReport>),
+
+    ///Message to share the objects that change object commands refer to
+    Objects(Vec<ObjectDescription>),
 }
@@ -1,15 +1,21 @@
 #![deny(missing_docs)]
 
 use crate::error::SlicerErrors;
-use crate::types::PartialInfillTypes;
+use crate::types::{PartialInfillTypes, SupportInterfacePattern};
 use serde::{Deserialize, Serialize};
 
+///Spacing in mm between arranged objects when the settings don't set it
+pub const DEFAULT_ARRANGE_SPACING: f64 = 6.0;
+
 ///A complete settings file for the entire slicer.
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Settings {
     ///The height of the layers
     pub layer_height: f64,
 
+    ///The adaptive layer height settings, if None the layer height will be used
+    pub adaptive_layer_height: Option<AdaptiveLayerHeightSettings>,
+
     ///The extrusion width of the layers
     pub layer_width: f64,
 
@@ -25,6 +31,9 @@ pub struct Settings {
     ///The support settings, if None no support will be generated
     pub support: Option<SupportSettings>,
 
+    ///The mesh repair settings, if None the models will not be repaired
+    pub repair: Option<RepairSettings>,
+
     ///Diameter of the nozzle in mm
     pub nozzle_diameter: f64,
 
@@ -70,6 +79,15 @@ pub struct Settings {
     ///Width of the brim, if None no brim will be generated
     pub brim_width: Option<f64>,
 
+    ///Spacing in mm between objects when arranging them on the bed
+    pub arrange_spacing: f64,
+
+    ///Print the outer perimeter of the layers above the bottom layers as one continuous spiral
+    pub vase_mode: bool,
+
+    ///The sequential printing settings, if None the objects are printed together layer by layer
+    pub sequential_printing: Option<SequentialPrintingSettings>,
+
     ///Inset the layer by the provided amount, if None on inset will be performed
     pub layer_shrink_amount: Option<f64>,
 
@@ -96,6 +114,7 @@ impl Default for Settings {
     fn default() -> Self {
         Settings {
             layer_height: 0.15,
+            adaptive_layer_height: None,
             number_of_perimeters: 3,
             top_layers: 3,
             bottom_layers: 3,
@@ -110,6 +129,8 @@ impl Default for Settings {
 
             support: None,
 
+            repair: None,
+
             speed: MovementParameter {
                 inner_perimeter: 5.0,
                 outer_perimeter: 5.0,
@@ -119,6 +140,7 @@ impl Default for Settings {
                 travel: 180.0,
                 bridge: 30.0,
                 support: 50.0,
+                support_interface: Some(30.0),
             },
             acceleration: MovementParameter {
                 inner_perimeter: 800.0,
@@ -129,6 +151,7 @@ impl Default for Settings {
                 travel: 1000.0,
                 bridge: 1000.0,
                 support: 1000.0,
+                support_interface: Some(1000.0),
             },
 
             infill_percentage: 0.2,
@@ -164,6 +187,9 @@ impl Default for Settings {
                                 M107 ; disable fan\n"
                 .to_string(),
             brim_width: None,
+            arrange_spacing: DEFAULT_ARRANGE_SPACING,
+            vase_mode: false,
+            sequential_printing: None,
             layer_settings: vec![(
                 LayerRange::SingleLayer(0),
                 PartialLayerSettings {
@@ -177,6 +203,7 @@ impl Default for Settings {
                         travel: 5.0,
                         bridge: 20.0,
                         support: 20.0,
+                        support_interface: Some(20.0),
                     }),
                     layer_height: Some(0.3),
                     bed_temp: Some(60.0),
@@ -192,16 +219,7 @@ impl Default for Settings {
 impl Settings {
     ///Get the layer settings for a specific layer index and height
     pub fn get_layer_settings(&self, layer: usize, height: f64) -> LayerSettings {
-        let changes = self
-            .layer_settings
-            .iter()
-            .filter(|(layer_range, _)| match layer_range {
-                LayerRange::LayerCountRange { end, start } => *start <= layer && layer <= *end,
-                LayerRange::HeightRange { end, start } => *start <= height && height <= *end,
-                LayerRange::SingleLayer(filter_layer) => *filter_layer == layer,
-            })
-            .map(|(_lr, pls)| pls)
-            .fold(PartialLayerSettings::default(), |a, b| a.combine(b));
+        let changes = self.get_layer_changes(layer, height);
 
         LayerSettings {
             layer_height: changes.layer_height.unwrap_or(self.layer_height),
@@ -225,9 +243,27 @@ impl Settings {
             extruder_temp: changes.extruder_temp.unwrap_or(self.filament.extruder_temp),
         }
     }
+
+    ///Get the layer height set by the layer settings for a specific layer index and height, if any
+    pub fn get_layer_height_change(&self, layer: usize, height: f64) -> Option<f64> {
+        self.get_layer_changes(layer, height).layer_height
+    }
+
+    fn get_layer_changes(&self, layer: usize, height: f64) -> PartialLayerSettings {
+        self.layer_settings
+            .iter()
+            .filter(|(layer_range, _)| match layer_range {
+                LayerRange::LayerCountRange { end, start } => *start <= layer && layer <= *end,
+                LayerRange::HeightRange { end, start } => *start <= height && height <= *end,
+                LayerRange::SingleLayer(filter_layer) => *filter_layer == layer,
+            })
+            .map(|(_lr, pls)| pls)
+            .fold(PartialLayerSettings::default(), |a, b| a.combine(b))
+    }
 }
 
 ///Settings specific to a Layer
+#[derive(Clone)]
 pub struct LayerSettings {
     ///The height of the layers
     pub layer_height: f64,
@@ -263,6 +299,34 @@ pub struct LayerSettings {
     pub extruder_temp: f64,
 }
 
+impl LayerSettings {
+    ///Create a copy of the layer settings with the changes applied
+    pub fn with_changes(&self, changes: &PartialLayerSettings) -> LayerSettings {
+        LayerSettings {
+            layer_height: changes.layer_height.unwrap_or(self.layer_height),
+            layer_shrink_amount: changes.layer_shrink_amount.or(self.layer_shrink_amount),
+            speed: changes.speed.clone().unwrap_or_else(|| self.speed.clone()),
+            acceleration: changes
+                .acceleration
+                .clone()
+                .unwrap_or_else(|| self.acceleration.clone()),
+            layer_width: changes.layer_width.unwrap_or(self.layer_width),
+            partial_infill_type: changes
+                .partial_infill_type
+                .unwrap_or(self.partial_infill_type),
+            infill_percentage: changes.infill_percentage.unwrap_or(self.infill_percentage),
+            infill_perimeter_overlap_percentage: changes
+                .infill_perimeter_overlap_percentage
+                .unwrap_or(self.infill_perimeter_overlap_percentage),
+            inner_perimeters_first: changes
+                .inner_perimeters_first
+                .unwrap_or(self.inner_perimeters_first),
+            bed_temp: changes.bed_temp.unwrap_or(self.bed_temp),
+            extruder_temp: changes.extruder_temp.unwrap_or(self.extruder_temp),
+        }
+    }
+}
+
 ///A set of values for different movement types
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct MovementParameter {
@@ -289,6 +353,10 @@ pub struct MovementParameter {
 
     ///Value for support structures
     pub support: f64,
+
+    ///Value for support interface layers, if None the support value is used
+    #[serde(default)]
+    pub support_interface: Option<f64>,
 }
 
 ///Settings for a filament
@@ -357,6 +425,62 @@ pub struct SupportSettings {
 
     ///Spacing between the ribs of support
     pub support_spacing: f64,
+
+    ///Dense layers of support next to the model, if None only the ribs are printed
+    #[serde(default)]
+    pub interface: Option<SupportInterfaceSettings>,
+}
+
+///Settings for the dense layers of support next to the model
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SupportInterfaceSettings {
+    ///Number of dense layers at the top of the support, under the model
+    pub top_layers: usize,
+
+    ///Number of dense layers at the bottom of the support, where it rests on the model
+    pub bottom_layers: usize,
+
+    ///Fraction of the interface area filled, 1.0 is solid
+    pub density: f64,
+
+    ///Pattern of the interface layers
+    pub pattern: SupportInterfacePattern,
+
+    ///Vertical gap in mm between the support and the model above and below it
+    pub z_gap: f64,
+
+    ///Horizontal gap in mm between the support and the model on the same layer
+    pub xy_gap: f64,
+}
+
+///The Settings for choosing the layer height from the slope of the model
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AdaptiveLayerHeightSettings {
+    ///The thinnest layer height, used for nearly flat surfaces
+    pub min_layer_height: f64,
+
+    ///The thickest layer height, used for vertical walls
+    pub max_layer_height: f64,
+}
+
+///The Settings for printing each object to completion before starting the next
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SequentialPrintingSettings {
+    ///Radius in mm around the nozzle that the extruder needs to be clear of printed objects
+    pub extruder_clearance_radius: f64,
+
+    ///Height in mm above the bed of the bottom of the gantry, only the last object can be taller
+    pub gantry_height: f64,
+}
+
+///The Settings for repairing models before slicing
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RepairSettings {
+    ///Vertices closer than this distance in mm are merged
+    pub merge_distance: f64,
+
+    ///The largest hole, in number of edges, that will be closed
+    pub max_hole_edges: usize,
 }
 
 ///The Settings for Skirt generation
@@ -370,11 +494,14 @@ pub struct SkirtSettings {
 }
 
 ///A partial complete settings file
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
 pub struct PartialSettings {
     ///The height of the layers
     pub layer_height: Option<f64>,
 
+    ///The adaptive layer height settings, if None the layer height will be used
+    pub adaptive_layer_height: Option<AdaptiveLayerHeightSettings>,
+
     ///The extrusion width of the layers
     pub layer_width: Option<f64>,
 
@@ -388,6 +515,8 @@ pub struct PartialSettings {
     pub skirt: Option<SkirtSettings>,
     ///The support settings, if None no support will be generated
     pub support: Option<SupportSettings>,
+    ///The mesh repair settings, if None the models will not be repaired
+    pub repair: Option<RepairSettings>,
     ///Diameter of the nozzle in mm
     pub nozzle_diameter: Option<f64>,
 
@@ -432,6 +561,15 @@ pub struct PartialSettings {
     ///Width of the brim, if None no brim will be generated
     pub brim_width: Option<f64>,
 
+    ///Spacing in mm between objects when arranging them on the bed, if None the default spacing is used
+    pub arrange_spacing: Option<f64>,
+
+    ///Print the outer perimeter of the layers above the bottom layers as one continuous spiral
+    pub vase_mode: Option<bool>,
+
+    ///The sequential printing settings, if None the objects are printed together layer by layer
+    pub sequential_printing: Option<SequentialPrintingSettings>,
+
     ///The minimum travel distance required to perform a retraction
     pub minimum_retract_distance: Option<f64>,
 
@@ -457,14 +595,34 @@ pub struct PartialSettings {
 impl PartialSettings {
     ///Convert a partial settings file into a complete settings file
     /// returns an error if a settings is not present in this or any sub file
-    pub fn get_settings(mut self) -> Result<Settings, SlicerErrors> {
+    pub fn get_settings(self) -> Result<Settings, SlicerErrors> {
+        self.get_settings_with_fallback(PartialSettings::default())
+    }
+
+    ///Convert a partial settings file into a complete settings file, using the fallback for any settings not present
+    /// returns an error if a settings is not present in this, any sub file or the fallback
+    pub fn get_settings_with_fallback(
+        mut self,
+        fallback: PartialSettings,
+    ) -> Result<Settings, SlicerErrors> {
         self.combine_with_other_files()?;
 
-        try_convert_partial_to_settings(self).map_err(|err| {
+        let settings = try_convert_partial_to_settings(self.combine(fallback)).map_err(|err| {
             SlicerErrors::SettingsFileMissingSettings {
                 missing_setting: err,
             }
-        })
+        })?;
+
+        //Vertices are never merged and no triangle is degenerate without a positive merge distance
+        if let Some(repair) = &settings.repair {
+            if repair.merge_distance.is_nan() || repair.merge_distance <= 0.0 {
+                return Err(SlicerErrors::SettingNotPositive {
+                    setting: "repair.merge_distance".to_string(),
+                });
+            }
+        }
+
+        Ok(settings)
     }
 
     fn combine_with_other_files(&mut self) -> Result<(), SlicerErrors> {
@@ -493,15 +651,21 @@ impl PartialSettings {
         Ok(())
     }
 
-    fn combine(&self, other: PartialSettings) -> PartialSettings {
+    ///Combine two partial settings, the values in self take priority over those in other
+    pub fn combine(&self, other: PartialSettings) -> PartialSettings {
         PartialSettings {
             layer_height: self.layer_height.or(other.layer_height),
+            adaptive_layer_height: self
+                .adaptive_layer_height
+                .clone()
+                .or_else(|| other.adaptive_layer_height.clone()),
             layer_width: self.layer_width.or(other.layer_width),
             layer_shrink_amount: self.layer_shrink_amount.or(other.layer_shrink_amount),
             filament: self.filament.clone().or_else(|| other.filament.clone()),
             fan: self.fan.clone().or_else(|| other.fan.clone()),
             skirt: self.skirt.clone().or_else(|| other.skirt.clone()),
             support: self.support.clone().or_else(|| other.support.clone()),
+            repair: self.repair.clone().or_else(|| other.repair.clone()),
             nozzle_diameter: self.nozzle_diameter.or(other.nozzle_diameter),
             retract_length: self.retract_length.or(other.retract_length),
             retract_lift_z: self.retract_lift_z.or(other.retract_lift_z),
@@ -520,6 +684,12 @@ impl PartialSettings {
             print_y: self.print_y.or(other.print_y),
             print_z: self.print_z.or(other.print_z),
             brim_width: self.brim_width.or(other.brim_width),
+            arrange_spacing: self.arrange_spacing.or(other.arrange_spacing),
+            vase_mode: self.vase_mode.or(other.vase_mode),
+            sequential_printing: self
+                .sequential_printing
+                .clone()
+                .or_else(|| other.sequential_printing.clone()),
             minimum_retract_distance: self
                 .minimum_retract_distance
                 .or(other.minimum_retract_distance),
@@ -552,6 +722,47 @@ impl PartialSettings {
     }
 }
 
+impl From<Settings> for PartialSettings {
+    fn from(settings: Settings) -> Self {
+        PartialSettings {
+            layer_height: Some(settings.layer_height),
+            adaptive_layer_height: settings.adaptive_layer_height,
+            layer_width: Some(settings.layer_width),
+            layer_shrink_amount: settings.layer_shrink_amount,
+            filament: Some(settings.filament),
+            fan: Some(settings.fan),
+            skirt: settings.skirt,
+            support: settings.support,
+            repair: settings.repair,
+            nozzle_diameter: Some(settings.nozzle_diameter),
+            retract_length: Some(settings.retract_length),
+            retract_lift_z: Some(settings.retract_lift_z),
+            retract_speed: Some(settings.retract_speed),
+            speed: Some(settings.speed),
+            acceleration: Some(settings.acceleration),
+            infill_percentage: Some(settings.infill_percentage),
+            inner_perimeters_first: Some(settings.inner_perimeters_first),
+            number_of_perimeters: Some(settings.number_of_perimeters),
+            top_layers: Some(settings.top_layers),
+            bottom_layers: Some(settings.bottom_layers),
+            print_x: Some(settings.print_x),
+            print_y: Some(settings.print_y),
+            print_z: Some(settings.print_z),
+            brim_width: settings.brim_width,
+            arrange_spacing: Some(settings.arrange_spacing),
+            vase_mode: Some(settings.vase_mode),
+            sequential_printing: settings.sequential_printing,
+            minimum_retract_distance: Some(settings.minimum_retract_distance),
+            infill_perimeter_overlap_percentage: Some(settings.infill_perimeter_overlap_percentage),
+            partial_infill_type: Some(settings.partial_infill_type),
+            starting_instructions: Some(settings.starting_instructions),
+            ending_instructions: Some(settings.ending_instructions),
+            other_files: None,
+            layer_settings: Some(settings.layer_settings),
+        }
+    }
+}
+
 /// The different types of layer ranges supported
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub enum LayerRange {
@@ -642,11 +853,13 @@ impl PartialLayerSettings {
 fn try_convert_partial_to_settings(part: PartialSettings) -> Result<Settings, String> {
     Ok(Settings {
         layer_height: part.layer_height.ok_or("layer_height")?,
+        adaptive_layer_height: part.adaptive_layer_height,
         layer_width: part.layer_width.ok_or("layer_width")?,
         filament: part.filament.ok_or("filament")?,
         fan: part.fan.ok_or("fan")?,
         skirt: part.skirt,
         support: part.support,
+        repair: part.repair,
         nozzle_diameter: part.nozzle_diameter.ok_or("nozzle_diameter")?,
         retract_length: part.retract_length.ok_or("retract_length")?,
         retract_lift_z: part.retract_lift_z.ok_or("retract_lift_z")?,
@@ -664,6 +877,9 @@ fn try_convert_partial_to_settings(part: PartialSettings) -> Result<Settings, St
         print_y: part.print_y.ok_or("print_y")?,
         print_z: part.print_z.ok_or("print_z")?,
         brim_width: part.brim_width,
+        arrange_spacing: part.arrange_spacing.unwrap_or(DEFAULT_ARRANGE_SPACING),
+        vase_mode: part.vase_mode.unwrap_or(false),
+        sequential_printing: part.sequential_printing,
         layer_shrink_amount: part.layer_shrink_amount,
         minimum_retract_distance: part
             .minimum_retract_distance
@@ -1,13 +1,14 @@
 #![deny(missing_docs)]
 
 use crate::error::SlicerErrors;
-use crate::settings::{LayerSettings, Settings};
+use crate::settings::{LayerSettings, PartialLayerSettings, PartialSettings, Settings};
 use geo::contains::Contains;
 use geo::prelude::SimplifyVW;
 use geo::simplifyvw::SimplifyVWPreserve;
+use geo::translate::Translate;
 use geo::*;
 use itertools::Itertools;
-use nalgebra::Point3;
+use nalgebra::{Point3, Rotation3};
 use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
 
@@ -31,6 +32,18 @@ pub struct Slice {
     ///The move chains generaated by various passses. These chains can be reordered by the optomization process to create faster commands.
     pub chains: Vec<MoveChain>,
 
+    ///Regions of the slice from modifier volumes and the layer settings used for the sparse infill inside them
+    pub modifier_regions: Vec<(MultiPolygon<f64>, LayerSettings)>,
+
+    ///The area of the slice from support enforcer volumes where overhangs above are always supported
+    pub support_enforcers: MultiPolygon<f64>,
+
+    ///The area of the slice from support blocker volumes where no support is added
+    pub support_blockers: MultiPolygon<f64>,
+
+    ///The chain printed after all other chains as a spiral rising from the bottom to the top of the slice, used in vase mode
+    pub spiral_chain: Option<MoveChain>,
+
     ///The lower height of this slice.
     pub bottom_height: f64,
 
@@ -64,6 +77,10 @@ impl Slice {
             support_tower: None,
             fixed_chains: vec![],
             chains: vec![],
+            modifier_regions: vec![],
+            support_enforcers: MultiPolygon(vec![]),
+            support_blockers: MultiPolygon(vec![]),
+            spiral_chain: None,
             bottom_height,
             top_height,
             layer_settings,
@@ -122,6 +139,10 @@ impl Slice {
             support_tower: None,
             chains: vec![],
             fixed_chains: vec![],
+            modifier_regions: vec![],
+            support_enforcers: MultiPolygon(vec![]),
+            support_blockers: MultiPolygon(vec![]),
+            spiral_chain: None,
             bottom_height,
             top_height,
             layer_settings,
@@ -132,6 +153,42 @@ impl Slice {
     pub fn get_height(&self) -> f64 {
         (self.bottom_height + self.top_height) / 2.0
     }
+
+    ///Create a copy of the slice and all of its moves moved by the offset
+    pub fn translated_copy(&self, offset: Coordinate<f64>) -> Self {
+        let translate = |polygon: &MultiPolygon<f64>| polygon.translate(offset.x, offset.y);
+
+        Slice {
+            main_polygon: translate(&self.main_polygon),
+            remaining_area: translate(&self.remaining_area),
+            support_interface: self.support_interface.as_ref().map(translate),
+            support_tower: self.support_tower.as_ref().map(translate),
+            fixed_chains: self
+                .fixed_chains
+                .iter()
+                .map(|chain| chain.translated_copy(offset))
+                .collect(),
+            chains: self
+                .chains
+                .iter()
+                .map(|chain| chain.translated_copy(offset))
+                .collect(),
+            modifier_regions: self
+                .modifier_regions
+                .iter()
+                .map(|(region, layer_settings)| (translate(region), layer_settings.clone()))
+                .collect(),
+            support_enforcers: translate(&self.support_enforcers),
+            support_blockers: translate(&self.support_blockers),
+            spiral_chain: self
+                .spiral_chain
+                .as_ref()
+                .map(|chain| chain.translated_copy(offset)),
+            bottom_height: self.bottom_height,
+            top_height: self.top_height,
+            layer_settings: self.layer_settings.clone(),
+        }
+    }
 }
 
 ///Types of solid infill
@@ -160,8 +217,18 @@ pub enum PartialInfillTypes {
     Lightning,
 }
 
+///Patterns for support interface layers
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum SupportInterfacePattern {
+    ///Back and forth lines that change direction each layer
+    Linear,
+
+    ///Back and forth lines and their perpendicular lines on every layer
+    Rectilinear,
+}
+
 ///A single 3D vertex
-#[derive(Default, Clone, Copy, Debug, PartialEq, Deserialize)]
+#[derive(Default, Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 #[serde(rename = "vertex")]
 pub struct Vertex {
     ///X Coordinate
@@ -218,25 +285,113 @@ impl Transform {
             [0., 0., 0., 1.],
         ])
     }
+
+    ///create a new transform for scaling along each axis, negative scales mirror along that axis
+    pub fn new_scale_transform(x: f64, y: f64, z: f64) -> Self {
+        Transform([
+            [x, 0., 0., 0.],
+            [0., y, 0., 0.],
+            [0., 0., z, 0.],
+            [0., 0., 0., 1.],
+        ])
+    }
+
+    ///Returns true if the transform mirrors models, reversing the winding of their triangles
+    pub fn is_mirroring(&self) -> bool {
+        let m = &self.0;
+        let determinant = m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0]);
+
+        determinant < 0.0
+    }
+
+    ///create a new transform for rotation around the x axis, then the y axis, then the z axis. Angles are in degrees
+    pub fn new_rotation_transform(x: f64, y: f64, z: f64) -> Self {
+        let rotation = Rotation3::from_euler_angles(x.to_radians(), y.to_radians(), z.to_radians());
+        let m = rotation.matrix();
+
+        Transform([
+            [m[(0, 0)], m[(0, 1)], m[(0, 2)], 0.],
+            [m[(1, 0)], m[(1, 1)], m[(1, 2)], 0.],
+            [m[(2, 0)], m[(2, 1)], m[(2, 2)], 0.],
+            [0., 0., 0., 1.],
+        ])
+    }
 }
 
-/*
-impl std::ops::Mul<Transform> for Transform{
+impl std::ops::Mul<&Transform> for &Transform {
     type Output = Transform;
 
-    fn mul(self, rhs: Transform) -> Self::Output {
-        let arrays = [[0.0 ; 4];4];
-        for 0..4
+    ///Combine two transforms, the result applies rhs then self
+    fn mul(self, rhs: &Transform) -> Self::Output {
+        let mut arrays = [[0.0; 4]; 4];
+
+        for (row, array_row) in arrays.iter_mut().enumerate() {
+            for (column, value) in array_row.iter_mut().enumerate() {
+                *value = (0..4).map(|i| self.0[row][i] * rhs.0[i][column]).sum();
+            }
+        }
 
         Transform(arrays)
     }
 }
-*/
+
+///How an input model is used in the print
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub enum VolumeType {
+    ///The model is printed
+    #[default]
+    Part,
+
+    ///The model is not printed, the sparse infill of the parts inside it uses these layer settings
+    Modifier(Box<PartialLayerSettings>),
+
+    ///The model is not printed and is cut out of the parts it overlaps
+    NegativeVolume,
+
+    ///The model is not printed, overhangs inside it are supported whatever their angle
+    SupportEnforcer,
+
+    ///The model is not printed, no support is added inside it
+    SupportBlocker,
+}
 
 ///A object is the collection of slices for a particular model.
 pub struct Object {
     /// The slices for this model sorted from lowest to highest.
     pub layers: Vec<Slice>,
+
+    ///The name of the model, from the model file if it provides one or the file name otherwise
+    pub name: String,
+
+    ///Metadata of the model from the model file as name and value pairs
+    pub metadata: Vec<(String, String)>,
+
+    ///Offsets in x and y of the additional copies of this object
+    pub copies: Vec<Coordinate<f64>>,
+
+    ///The settings for this object, the global settings with any overrides for the object applied
+    pub settings: Settings,
+}
+
+///The name and metadata of an object, commands refer to objects by their index in a list of these
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ObjectDescription {
+    ///The name of the object
+    pub name: String,
+
+    ///Metadata of the object from the model file as name and value pairs
+    pub metadata: Vec<(String, String)>,
+}
+
+impl From<&Object> for ObjectDescription {
+    fn from(object: &Object) -> Self {
+        ObjectDescription {
+            name: object.name.clone(),
+            metadata: object.metadata.clone(),
+        }
+    }
 }
 
 ///The different types of input that the slicer can take.
@@ -250,6 +405,96 @@ pub enum InputObject {
 
     ///Automatically Center and raise the model for printing but offset it by x and y
     AutoTranslate(String, f64, f64),
+
+    ///Scale, mirror and rotate the model in that order then automatically center and raise it for printing.
+    /// Auto orientation is applied after the rotation.
+    Transformed {
+        ///The model file to load
+        path: String,
+
+        ///Scale of the model, either a single value or a value for each axis
+        #[serde(default)]
+        scale: Option<Scale>,
+
+        ///Rotation around the x, y and z axes in degrees
+        #[serde(default)]
+        rotation: Option<[f64; 3]>,
+
+        ///Mirror the model along the x, y and z axes
+        #[serde(default)]
+        mirror: Option<[bool; 3]>,
+
+        ///Offset from the center of the bed in mm, z is applied after the model is dropped to the bed
+        #[serde(default)]
+        translation: Option<[f64; 3]>,
+
+        ///Rotate the model to the orientation needing the least support
+        #[serde(default)]
+        auto_orient: bool,
+
+        ///Print multiple copies of the model, the model is only sliced once
+        #[serde(default)]
+        copies: Option<Copies>,
+
+        ///Settings for this model that override the global settings
+        #[serde(default)]
+        settings: Option<Box<PartialSettings>>,
+
+        ///How the model is used, by default it is printed
+        #[serde(default)]
+        volume: VolumeType,
+
+        ///Cut the model along a plane into two parts that are sliced as separate objects
+        #[serde(default)]
+        cut: Option<Cut>,
+    },
+}
+
+///Copies of an input model
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum Copies {
+    ///Total number of copies, including the original, arranged on the bed
+    Count(usize),
+
+    ///Offset in x and y from the center of the bed in mm for each copy, added to the translation
+    Placements(Vec<[f64; 2]>),
+}
+
+///A plane to cut an input model along
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Cut {
+    ///Direction the plane faces, the part on this side of the plane is the second part
+    pub normal: [f64; 3],
+
+    ///Distance in mm along the normal from the center of the bottom of the transformed model to the plane
+    #[serde(default)]
+    pub distance: f64,
+
+    ///Holes for alignment pins added to both cut faces
+    #[serde(default)]
+    pub pins: Option<PinHoles>,
+}
+
+///Holes for alignment pins in the cut faces of a model
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PinHoles {
+    ///Diameter of the holes in mm
+    pub diameter: f64,
+
+    ///Depth of the holes into each part in mm
+    pub depth: f64,
+}
+
+///Scale for an input model
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(untagged)]
+pub enum Scale {
+    ///Scale all axes by the same amount
+    Uniform(f64),
+
+    ///Scale each of the x, y and z axes separately
+    PerAxis([f64; 3]),
 }
 
 impl InputObject {
@@ -259,6 +504,7 @@ impl InputObject {
             InputObject::Raw(str, _) => str,
             InputObject::Auto(str) => str,
             InputObject::AutoTranslate(str, _, _) => str,
+            InputObject::Transformed { path, .. } => path,
         }
     }
 }
@@ -321,9 +567,12 @@ pub enum MoveType {
     ///A bridge over open air
     Bridging,
 
-    ///Support towers and interface
+    ///Support towers
     Support,
 
+    ///Dense support layers touching the model above and below the support
+    SupportInterface,
+
     ///Standard travel moves without extrusion
     Travel,
 }
@@ -351,6 +600,24 @@ pub enum Command {
         width: f64,
     },
 
+    ///Move to a location and height while extruding plastic, the height changes steadily along the move
+    MoveAndExtrudeZ {
+        ///Start point of the move
+        start: Coordinate<f64>,
+
+        ///End point of the move
+        end: Coordinate<f64>,
+
+        ///The height of the print head at the end of the move
+        end_z: f64,
+
+        ///The height thickness of the move
+        thickness: f64,
+
+        /// The extrusion width
+        width: f64,
+    },
+
     ///Change the layer height
     LayerChange {
         ///The height the print head should move to
@@ -392,7 +659,7 @@ pub enum Command {
 
     ///Change the object that is being printed
     ChangeObject {
-        ///The index of the new object being changed to
+        ///The index of the new object being changed to in the object descriptions
         object: usize,
     },
     ///Used in optimization , should be optimized out
@@ -492,6 +759,22 @@ impl StateChange {
 }
 
 impl MoveChain {
+    ///Create a copy of the chain moved by the offset
+    pub fn translated_copy(&self, offset: Coordinate<f64>) -> Self {
+        MoveChain {
+            start_point: self.start_point + offset,
+            moves: self
+                .moves
+                .iter()
+                .map(|m| Move {
+                    end: m.end + offset,
+                    width: m.width,
+                    move_type: m.move_type,
+                })
+                .collect(),
+        }
+    }
+
     ///Convert a move chain into a list of commands
     pub fn create_commands(self, settings: &LayerSettings, thickness: f64) -> Vec<Command> {
         let mut cmds = vec![];
@@ -585,6 +868,28 @@ impl MoveChain {
                             },
                         });
                     }
+                    MoveType::SupportInterface => {
+                        cmds.push(Command::SetState {
+                            new_state: StateChange {
+                                bed_temp: None,
+                                extruder_temp: None,
+                                fan_speed: None,
+                                movement_speed: Some(
+                                    settings
+                                        .speed
+                                        .support_interface
+                                        .unwrap_or(settings.speed.support),
+                                ),
+                                acceleration: Some(
+                                    settings
+                                        .acceleration
+                                        .support_interface
+                                        .unwrap_or(settings.acceleration.support),
+                                ),
+                                retract: Some(false),
+                            },
+                        });
+                    }
                     MoveType::Travel => {
                         cmds.push(Command::SetState {
                             new_state: StateChange {
@@ -618,6 +923,58 @@ impl MoveChain {
         cmds
     }
 
+    ///Convert a move chain into a list of commands that rise steadily from the start height to the end height
+    pub fn create_spiral_commands(
+        self,
+        settings: &LayerSettings,
+        thickness: f64,
+        start_z: f64,
+        end_z: f64,
+    ) -> Vec<Command> {
+        let mut cmds = self.create_commands(settings, thickness);
+
+        let length = |start: &Coordinate<f64>, end: &Coordinate<f64>| {
+            ((end.x - start.x).powi(2) + (end.y - start.y).powi(2)).sqrt()
+        };
+
+        let total_length: f64 = cmds
+            .iter()
+            .map(|cmd| match cmd {
+                Command::MoveAndExtrude { start, end, .. } => length(start, end),
+                _ => 0.0,
+            })
+            .sum();
+
+        //A chain without any length has nowhere to rise along
+        if total_length == 0.0 {
+            return cmds;
+        }
+
+        let mut current_length = 0.0;
+
+        for cmd in cmds.iter_mut() {
+            if let Command::MoveAndExtrude {
+                start,
+                end,
+                thickness,
+                width,
+            } = *cmd
+            {
+                current_length += length(&start, &end);
+
+                *cmd = Command::MoveAndExtrudeZ {
+                    start,
+                    end,
+                    end_z: start_z + (end_z - start_z) * (current_length / total_length),
+                    thickness,
+                    width,
+                };
+            }
+        }
+
+        cmds
+    }
+
     ///Rotate all moves in the movechain by a specific angle in radians.
     pub fn rotate(&mut self, angle: f64) {
         let cos_a = angle.cos();
@@ -667,3 +1024,158 @@ impl CalculatedValues {
         )
     }
 }
+
+///The results of checking a model for problems that would stop it from printing
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ModelCheckReport {
+    ///The name of the model
+    pub name: String,
+
+    ///Number of triangles in the model
+    pub triangle_count: usize,
+
+    ///Number of separate volumes in the model
+    pub volume_count: usize,
+
+    ///Total volume enclosed by the model in mm^3
+    pub volume: f64,
+
+    ///Number of edges shared by more than two triangles
+    pub non_manifold_edges: usize,
+
+    ///Number of edges only used by one triangle
+    pub open_boundary_edges: usize,
+
+    ///Number of pairs of triangles that intersect each other
+    pub self_intersections: usize,
+
+    ///Number of triangles with normals facing into the model
+    pub inverted_normals: usize,
+
+    ///The minimum corner of the model's bounding box
+    pub min: Vertex,
+
+    ///The maximum corner of the model's bounding box
+    pub max: Vertex,
+
+    ///True if the bounding box is inside the build volume
+    pub fits_build_volume: bool,
+
+    ///The error from creating the model's tower, None if it was created successfully
+    pub tower_error: Option<SlicerErrors>,
+}
+
+impl ModelCheckReport {
+    ///Returns true if no problems were found with the model.
+    /// Self intersections are only reported as the overlapping regions are merged when slicing.
+    pub fn is_printable(&self) -> bool {
+        self.non_manifold_edges == 0
+            && self.open_boundary_edges == 0
+            && self.inverted_normals == 0
+            && self.fits_build_volume
+            && self.tower_error.is_none()
+    }
+}
+
+impl std::fmt::Display for ModelCheckReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "Model \"{}\": {}",
+            self.name,
+            if self.is_printable() {
+                "OK"
+            } else {
+                "PROBLEMS FOUND"
+            }
+        )?;
+        writeln!(f, "\tTriangles: {}", self.triangle_count)?;
+        writeln!(f, "\tVolumes: {}", self.volume_count)?;
+        writeln!(f, "\tVolume: {:.3} cm^3", self.volume / 1000.0)?;
+        writeln!(f, "\tNon-manifold edges: {}", self.non_manifold_edges)?;
+        writeln!(f, "\tOpen boundary edges: {}", self.open_boundary_edges)?;
+        writeln!(f, "\tSelf intersections: {}", self.self_intersections)?;
+        writeln!(f, "\tInverted normals: {}", self.inverted_normals)?;
+        writeln!(
+            f,
+            "\tBounding box: ({:.3}, {:.3}, {:.3}) to ({:.3}, {:.3}, {:.3}){}",
+            self.min.x,
+            self.min.y,
+            self.min.z,
+            self.max.x,
+            self.max.y,
+            self.max.z,
+            if self.fits_build_volume {
+                ""
+            } else {
+                " outside the build volume"
+            }
+        )?;
+
+        if let Some(err) = &self.tower_error {
+            let (code, message) = err.get_code_and_message();
+            writeln!(f, "\tTower creation failed: {:#X} {}", code, message)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spiral_rises_steadily_along_chain() {
+        let layer_settings = Settings::default().get_layer_settings(5, 1.0);
+        let square = [(10.0, 0.0), (10.0, 10.0), (0.0, 10.0), (0.0, 0.0)];
+        let chain = MoveChain {
+            start_point: Coordinate { x: 0.0, y: 0.0 },
+            moves: square
+                .iter()
+                .map(|&(x, y)| Move {
+                    end: Coordinate { x, y },
+                    move_type: MoveType::OuterPerimeter,
+                    width: 0.4,
+                })
+                .collect(),
+        };
+
+        let heights: Vec<f64> = chain
+            .create_spiral_commands(&layer_settings, 0.2, 1.0, 1.2)
+            .iter()
+            .filter_map(|cmd| match cmd {
+                Command::MoveAndExtrudeZ { end_z, .. } => Some(*end_z),
+                _ => None,
+            })
+            .collect();
+
+        assert_eq!(heights.len(), 4);
+        for (height, expected) in heights.iter().zip([1.05, 1.1, 1.15, 1.2]) {
+            assert!((height - expected).abs() < 0.000_001);
+        }
+    }
+
+    #[test]
+    fn spiral_without_length_has_no_ramp() {
+        let layer_settings = Settings::default().get_layer_settings(5, 1.0);
+        let point = Coordinate { x: 5.0, y: 5.0 };
+        let chain = MoveChain {
+            start_point: point,
+            moves: (0..3)
+                .map(|_| Move {
+                    end: point,
+                    move_type: MoveType::OuterPerimeter,
+                    width: 0.4,
+                })
+                .collect(),
+        };
+
+        let commands = chain.create_spiral_commands(&layer_settings, 0.2, 1.0, 1.2);
+
+        assert!(!commands.is_empty());
+        assert!(!commands
+            .iter()
+            .any(|cmd| matches!(cmd, Command::MoveAndExtrudeZ { .. })));
+    }
+}
@@ -0,0 +1,139 @@
+use crate::input::InputModel;
+use gladius_shared::settings::AdaptiveLayerHeightSettings;
+
+///The range of heights a facet covers and the tallest layer that keeps its stair step small
+#[derive(Clone, Copy)]
+struct SlopedFacet {
+    min_z: f64,
+    max_z: f64,
+    max_layer_height: f64,
+}
+
+///Chooses each layer height from the slopes of the facets in the layer, the layers must be requested from the bottom up.
+/// The stair step of a facet is the layer height times the z component of its normal, each layer is as tall as possible
+/// while keeping the stair step of every facet no larger than the min layer height.
+pub struct AdaptiveLayerHeights {
+    facets: Vec<SlopedFacet>,
+    next_facet: usize,
+    active_facets: Vec<SlopedFacet>,
+    min_layer_height: f64,
+    max_layer_height: f64,
+}
+
+impl AdaptiveLayerHeights {
+    pub fn new(model: &InputModel, settings: &AdaptiveLayerHeightSettings) -> Self {
+        let mut facets: Vec<SlopedFacet> = model
+            .triangles
+            .iter()
+            .filter_map(|triangle| {
+                let [v0, v1, v2] = triangle.verts.map(|index| model.vertices[index]);
+
+                let min_z = v0.z.min(v1.z).min(v2.z);
+                let max_z = v0.z.max(v1.z).max(v2.z);
+
+                //Flat facets are on a single layer so have no stair steps
+                if max_z <= min_z {
+                    return None;
+                }
+
+                let (ax, ay, az) = (v1.x - v0.x, v1.y - v0.y, v1.z - v0.z);
+                let (bx, by, bz) = (v2.x - v0.x, v2.y - v0.y, v2.z - v0.z);
+                let (nx, ny, nz) = (ay * bz - az * by, az * bx - ax * bz, ax * by - ay * bx);
+                let normal_z = nz.abs() / (nx * nx + ny * ny + nz * nz).sqrt();
+
+                Some(SlopedFacet {
+                    min_z,
+                    max_z,
+                    max_layer_height: (settings.min_layer_height / normal_z)
+                        .min(settings.max_layer_height),
+                })
+            })
+            .collect();
+
+        facets.sort_by(|a, b| a.min_z.partial_cmp(&b.min_z).unwrap());
+
+        AdaptiveLayerHeights {
+            facets,
+            next_facet: 0,
+            active_facets: vec![],
+            min_layer_height: settings.min_layer_height,
+            max_layer_height: settings.max_layer_height,
+        }
+    }
+
+    ///Get the height of the layer starting at the bottom height
+    pub fn get_layer_height(&mut self, bottom_height: f64) -> f64 {
+        let top_height = bottom_height + self.max_layer_height;
+
+        while let Some(facet) = self.facets.get(self.next_facet) {
+            if facet.min_z >= top_height {
+                break;
+            }
+            self.active_facets.push(*facet);
+            self.next_facet += 1;
+        }
+
+        self.active_facets
+            .retain(|facet| facet.max_z > bottom_height);
+
+        //The active facets are sorted by their bottom so the layer shrinks until no more facets are in it,
+        // a layer can end where a facet starts if that is taller than the facet allows
+        let mut layer_height = self.max_layer_height;
+        for facet in &self.active_facets {
+            if facet.min_z >= bottom_height + layer_height {
+                break;
+            }
+            layer_height =
+                layer_height.min(facet.max_layer_height.max(facet.min_z - bottom_height));
+        }
+
+        layer_height.max(self.min_layer_height)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gladius_shared::settings::Settings;
+    use gladius_shared::types::{IndexedTriangle, Vertex, VolumeType};
+
+    #[test]
+    fn slopes_get_thinner_layers_than_walls() {
+        let settings = AdaptiveLayerHeightSettings {
+            min_layer_height: 0.1,
+            max_layer_height: 0.3,
+        };
+
+        //A vertical wall from 0 to 10 and a 45 degree slope from 10 to 20
+        let vertex = |x: f64, y: f64, z: f64| Vertex { x, y, z };
+        let model = InputModel {
+            name: "wall and slope".to_string(),
+            metadata: vec![],
+            vertices: vec![
+                vertex(0.0, 0.0, 0.0),
+                vertex(10.0, 0.0, 0.0),
+                vertex(0.0, 0.0, 10.0),
+                vertex(10.0, 0.0, 10.0),
+                vertex(0.0, 10.0, 20.0),
+            ],
+            triangles: vec![
+                IndexedTriangle { verts: [0, 1, 2] },
+                IndexedTriangle { verts: [2, 3, 4] },
+            ],
+            copies: vec![],
+            settings: Settings::default(),
+            volume: VolumeType::Part,
+        };
+
+        let mut layer_heights = AdaptiveLayerHeights::new(&model, &settings);
+
+        assert_eq!(layer_heights.get_layer_height(0.0), 0.3);
+        assert_eq!(layer_heights.get_layer_height(5.0), 0.3);
+
+        //The layer ends where the slope starts
+        assert!((layer_heights.get_layer_height(9.8) - 0.2).abs() < 0.000_001);
+
+        let slope_height = layer_heights.get_layer_height(15.0);
+        assert!((slope_height - 0.1 * std::f64::consts::SQRT_2).abs() < 0.000_001);
+    }
+}
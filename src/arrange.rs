@@ -0,0 +1,423 @@
+use crate::input::InputModel;
+use geo::Coordinate;
+use gladius_shared::error::SlicerErrors;
+use gladius_shared::settings::Settings;
+use gladius_shared::types::VolumeType;
+use std::ops::Range;
+
+///Models that are arranged together
+pub struct ArrangeGroup {
+    ///Range of the models in the group
+    pub models: Range<usize>,
+
+    ///The models stay where they are and only their copies are arranged, as close to them as possible
+    pub fixed: bool,
+}
+
+///Footprint of a group of models or one of its copies on the bed
+struct Footprint {
+    group: Range<usize>,
+    copy: Option<usize>,
+    anchor: Option<(f64, f64)>,
+    min_x: f64,
+    min_y: f64,
+    width: f64,
+    depth: f64,
+}
+
+///Area on the bed covered by a placed object
+struct Area {
+    min_x: f64,
+    min_y: f64,
+    max_x: f64,
+    max_y: f64,
+}
+
+impl Area {
+    fn overlaps(&self, other: &Area, gap: f64) -> bool {
+        self.min_x < other.max_x + gap
+            && other.min_x < self.max_x + gap
+            && self.min_y < other.max_y + gap
+            && other.min_y < self.max_y + gap
+    }
+}
+
+///Move each group of models and place their copies so their footprints don't overlap.
+/// Printed models that aren't in a group or are in a fixed group stay where they are and their footprints are avoided, without them the arrangement is centered on the bed.
+/// Each footprint is placed at the lowest free position, or the closest one to a fixed original, leaving room for the brim around each object and the skirt around all of them.
+/// When printing one object at a time the spacing is at least the extruder clearance radius.
+pub fn arrange_models(
+    models: &mut [InputModel],
+    groups: &[ArrangeGroup],
+    settings: &Settings,
+) -> Result<(), SlicerErrors> {
+    let groups: Vec<&ArrangeGroup> = groups
+        .iter()
+        .filter(|group| !group.models.is_empty())
+        .collect();
+    let has_copies = groups
+        .iter()
+        .any(|group| !models[group.models.start].copies.is_empty());
+
+    //Models placed by the input and all their copies, the copies of fixed groups are still to be placed
+    let obstacles: Vec<Area> = models
+        .iter()
+        .enumerate()
+        .filter_map(|(index, model)| {
+            let group = groups.iter().find(|group| group.models.contains(&index));
+
+            match group {
+                _ if !matches!(model.volume, VolumeType::Part) => None,
+                None => Some((model, model.copies.as_slice())),
+                Some(group) if group.fixed => Some((model, &[][..])),
+                Some(_) => None,
+            }
+        })
+        .flat_map(|(model, copies)| {
+            let (min_x, max_x, min_y, max_y) = get_footprint_bounds(std::slice::from_ref(model));
+
+            std::iter::once(Coordinate { x: 0.0, y: 0.0 })
+                .chain(copies.iter().copied())
+                .map(move |copy| Area {
+                    min_x: min_x + copy.x,
+                    min_y: min_y + copy.y,
+                    max_x: max_x + copy.x,
+                    max_y: max_y + copy.y,
+                })
+        })
+        .collect();
+
+    if groups.is_empty() || (groups.len() < 2 && !has_copies && obstacles.is_empty()) {
+        return Ok(());
+    }
+
+    //Objects printed one at a time need room for the extruder between them
+    let spacing = settings
+        .sequential_printing
+        .as_ref()
+        .map(|sequential| {
+            settings
+                .arrange_spacing
+                .max(sequential.extruder_clearance_radius)
+        })
+        .unwrap_or(settings.arrange_spacing);
+
+    let brim_width = settings.brim_width.unwrap_or(0.0);
+    let skirt_width = settings
+        .skirt
+        .as_ref()
+        .map(|skirt| skirt.distance + settings.layer_width)
+        .unwrap_or(0.0);
+
+    let gap = spacing + brim_width * 2.0;
+    let margin = brim_width + skirt_width;
+
+    let mut footprints: Vec<Footprint> = groups
+        .iter()
+        .flat_map(|group| {
+            let (min_x, max_x, min_y, max_y) = get_footprint_bounds(&models[group.models.clone()]);
+            let copy_count = models[group.models.start].copies.len();
+            let anchor = group.fixed.then_some((min_x, min_y));
+
+            //The originals of fixed groups are obstacles instead
+            (!group.fixed)
+                .then_some(None)
+                .into_iter()
+                .chain((0..copy_count).map(Some))
+                .map(move |copy| Footprint {
+                    group: group.models.clone(),
+                    copy,
+                    anchor,
+                    min_x,
+                    min_y,
+                    width: max_x - min_x,
+                    depth: max_y - min_y,
+                })
+        })
+        .collect();
+
+    //Deepest objects first so the smaller ones fill the space beside them
+    footprints.sort_by(|a, b| {
+        b.depth
+            .partial_cmp(&a.depth)
+            .unwrap()
+            .then(b.width.partial_cmp(&a.width).unwrap())
+    });
+
+    let mut placed: Vec<Area> = vec![];
+
+    for footprint in &footprints {
+        //Candidates are the bed corner and the positions on each side of every placed footprint
+        let candidates = std::iter::once((margin, margin)).chain(
+            obstacles.iter().chain(placed.iter()).flat_map(|area| {
+                [
+                    (area.max_x + gap, area.min_y),
+                    (area.min_x, area.max_y + gap),
+                    (area.min_x - gap - footprint.width, area.min_y),
+                    (area.min_x, area.min_y - gap - footprint.depth),
+                    (area.max_x + gap, margin),
+                    (margin, area.max_y + gap),
+                ]
+            }),
+        );
+
+        let position = candidates
+            .map(|(x, y)| Area {
+                min_x: x,
+                min_y: y,
+                max_x: x + footprint.width,
+                max_y: y + footprint.depth,
+            })
+            .filter(|area| {
+                area.min_x >= margin
+                    && area.min_y >= margin
+                    && area.max_x <= settings.print_x - margin
+                    && area.max_y <= settings.print_y - margin
+                    && !obstacles
+                        .iter()
+                        .chain(placed.iter())
+                        .any(|other| area.overlaps(other, gap))
+            })
+            .min_by(|a, b| match footprint.anchor {
+                Some((x, y)) => {
+                    let distance = |area: &Area| (area.min_x - x).hypot(area.min_y - y);
+                    distance(a).partial_cmp(&distance(b)).unwrap()
+                }
+                None => a
+                    .min_y
+                    .partial_cmp(&b.min_y)
+                    .unwrap()
+                    .then(a.min_x.partial_cmp(&b.min_x).unwrap()),
+            })
+            .ok_or_else(|| SlicerErrors::ObjectsDoNotFit {
+                name: models[footprint.group.start].name.clone(),
+            })?;
+
+        placed.push(position);
+    }
+
+    //Without fixed objects the arrangement is moved to the center of the bed
+    let (center_x, center_y) = if obstacles.is_empty() {
+        let (min_x, max_x, min_y, max_y) = placed.iter().fold(
+            (
+                f64::INFINITY,
+                f64::NEG_INFINITY,
+                f64::INFINITY,
+                f64::NEG_INFINITY,
+            ),
+            |a, area| {
+                (
+                    a.0.min(area.min_x),
+                    a.1.max(area.max_x),
+                    a.2.min(area.min_y),
+                    a.3.max(area.max_y),
+                )
+            },
+        );
+
+        (
+            (settings.print_x - (max_x - min_x)) / 2.0 - min_x,
+            (settings.print_y - (max_y - min_y)) / 2.0 - min_y,
+        )
+    } else {
+        (0.0, 0.0)
+    };
+
+    let offsets: Vec<Coordinate<f64>> = footprints
+        .iter()
+        .zip(placed.iter())
+        .map(|(footprint, area)| Coordinate {
+            x: center_x + area.min_x - footprint.min_x,
+            y: center_y + area.min_y - footprint.min_y,
+        })
+        .collect();
+
+    //Copies are stored relative to the moved models so the originals must be moved first
+    for (footprint, offset) in footprints.iter().zip(offsets.iter()) {
+        if footprint.copy.is_none() {
+            for model in &mut models[footprint.group.clone()] {
+                for vertex in model.vertices.iter_mut() {
+                    vertex.x += offset.x;
+                    vertex.y += offset.y;
+                }
+            }
+        }
+    }
+
+    for (footprint, offset) in footprints.iter().zip(offsets.iter()) {
+        if let Some(copy) = footprint.copy {
+            let original_offset = footprints
+                .iter()
+                .zip(offsets.iter())
+                .find(|(other, _)| other.group == footprint.group && other.copy.is_none())
+                .map(|(_, original_offset)| *original_offset)
+                //Originals of fixed groups aren't moved
+                .unwrap_or(Coordinate { x: 0.0, y: 0.0 });
+
+            for model in &mut models[footprint.group.clone()] {
+                model.copies[copy] = *offset - original_offset;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+///Get the minimum and maximum x and y of the models
+fn get_footprint_bounds(models: &[InputModel]) -> (f64, f64, f64, f64) {
+    models.iter().flat_map(|model| model.vertices.iter()).fold(
+        (
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+        ),
+        |a, v| (a.0.min(v.x), a.1.max(v.x), a.2.min(v.y), a.3.max(v.y)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gladius_shared::settings::PartialSettings;
+    use gladius_shared::types::Vertex;
+
+    fn square(name: &str, size: f64) -> InputModel {
+        InputModel {
+            name: name.to_string(),
+            metadata: vec![],
+            vertices: vec![
+                Vertex {
+                    x: 0.0,
+                    y: 0.0,
+                    z: 0.0,
+                },
+                Vertex {
+                    x: size,
+                    y: size,
+                    z: 1.0,
+                },
+            ],
+            triangles: vec![],
+            copies: vec![],
+            settings: Settings::default(),
+            volume: VolumeType::Part,
+        }
+    }
+
+    fn groups(ranges: &[Range<usize>]) -> Vec<ArrangeGroup> {
+        ranges
+            .iter()
+            .map(|range| ArrangeGroup {
+                models: range.clone(),
+                fixed: false,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn objects_are_spaced_apart() {
+        let settings = Settings::default();
+        let mut models = vec![square("a", 20.0), square("b", 20.0)];
+
+        arrange_models(&mut models, &groups(&[0..1, 1..2]), &settings).unwrap();
+
+        let gap = models[1].vertices[0].x - models[0].vertices[1].x;
+        assert!((gap.abs() - settings.arrange_spacing).abs() < 0.000_001);
+
+        let mut models = vec![square("a", 20.0), square("big", settings.print_x + 1.0)];
+        assert!(matches!(
+            arrange_models(&mut models, &groups(&[0..1, 1..2]), &settings),
+            Err(SlicerErrors::ObjectsDoNotFit { name }) if name == "big"
+        ));
+    }
+
+    #[test]
+    fn copies_are_placed_apart() {
+        let settings = Settings::default();
+        let mut model = square("a", 20.0);
+        model.copies = vec![Coordinate { x: 0.0, y: 0.0 }; 2];
+        let mut models = vec![model];
+
+        let group = ArrangeGroup {
+            models: 0..1,
+            fixed: false,
+        };
+
+        arrange_models(&mut models, &[group], &settings).unwrap();
+
+        let spacing = settings.arrange_spacing;
+        let offsets: Vec<f64> = models[0].copies.iter().map(|copy| copy.x.abs()).collect();
+        assert!(offsets.contains(&(20.0 + spacing)));
+        assert!(offsets.contains(&((20.0 + spacing) * 2.0)));
+    }
+
+    #[test]
+    fn fixed_objects_are_avoided() {
+        let settings = Settings::default();
+        let mut fixed = square("fixed", 20.0);
+        fixed.copies = vec![Coordinate { x: 30.0, y: 0.0 }];
+        let mut models = vec![fixed, square("a", 20.0), square("b", 20.0)];
+
+        arrange_models(&mut models, &groups(&[1..2, 2..3]), &settings).unwrap();
+
+        assert_eq!(models[0].vertices[0].x, 0.0);
+        let fixed_areas = [(0.0, 20.0), (30.0, 50.0)];
+        for model in &models[1..] {
+            let (min_x, max_x, min_y, max_y) = get_footprint_bounds(std::slice::from_ref(model));
+            for (fixed_min_x, fixed_max_x) in fixed_areas {
+                assert!(
+                    min_x >= fixed_max_x + settings.arrange_spacing
+                        || max_x + settings.arrange_spacing <= fixed_min_x
+                        || min_y >= 20.0 + settings.arrange_spacing
+                        || max_y + settings.arrange_spacing <= 0.0
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn copies_are_placed_around_a_fixed_original() {
+        let settings = Settings::default();
+        let mut model = square("a", 20.0);
+        for vertex in model.vertices.iter_mut() {
+            vertex.x += 100.0;
+            vertex.y += 100.0;
+        }
+        model.copies = vec![Coordinate { x: 0.0, y: 0.0 }; 2];
+        let mut models = vec![model];
+        let group = ArrangeGroup {
+            models: 0..1,
+            fixed: true,
+        };
+
+        arrange_models(&mut models, &[group], &settings).unwrap();
+
+        assert_eq!(models[0].vertices[0].x, 100.0);
+        assert_eq!(models[0].vertices[0].y, 100.0);
+
+        //Each copy is right next to the original
+        let step = 20.0 + settings.arrange_spacing;
+        for copy in &models[0].copies {
+            assert!(copy.x.abs() == step || copy.y.abs() == step);
+            assert!(copy.x.abs().min(copy.y.abs()) == 0.0);
+        }
+        assert_ne!(models[0].copies[0], models[0].copies[1]);
+    }
+
+    #[test]
+    fn missing_spacing_uses_the_default() {
+        let partial = PartialSettings {
+            arrange_spacing: None,
+            ..Settings::default().into()
+        };
+        let settings = partial
+            .get_settings_with_fallback(PartialSettings::default())
+            .unwrap();
+
+        assert_eq!(
+            settings.arrange_spacing,
+            Settings::default().arrange_spacing
+        );
+    }
+}
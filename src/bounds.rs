@@ -0,0 +1,199 @@
+use crate::input::InputModel;
+use geo::Coordinate;
+use gladius_shared::error::SlicerErrors;
+use gladius_shared::settings::Settings;
+use gladius_shared::types::*;
+
+///Distance in mm that positions can be outside the build volume to allow for rounding
+const BOUNDS_TOLERANCE: f64 = 0.000_1;
+
+///Check that the models and their copies are inside the build volume
+pub fn check_model_bounds(models: &[InputModel], settings: &Settings) -> Result<(), SlicerErrors> {
+    for model in models {
+        let (min, max) = model.vertices.iter().fold(
+            ([f64::INFINITY; 3], [f64::NEG_INFINITY; 3]),
+            |(min, max), v| {
+                (
+                    [min[0].min(v.x), min[1].min(v.y), min[2].min(v.z)],
+                    [max[0].max(v.x), max[1].max(v.y), max[2].max(v.z)],
+                )
+            },
+        );
+
+        //The original is at no offset
+        let (min_offset, max_offset) = model.copies.iter().fold(
+            ([0.0, 0.0], [0.0, 0.0]),
+            |(min, max): ([f64; 2], [f64; 2]), copy| {
+                (
+                    [min[0].min(copy.x), min[1].min(copy.y)],
+                    [max[0].max(copy.x), max[1].max(copy.y)],
+                )
+            },
+        );
+
+        check_range(
+            Some(&model.name),
+            'x',
+            min[0] + min_offset[0],
+            max[0] + max_offset[0],
+            settings.print_x,
+        )?;
+        check_range(
+            Some(&model.name),
+            'y',
+            min[1] + min_offset[1],
+            max[1] + max_offset[1],
+            settings.print_y,
+        )?;
+        check_range(Some(&model.name), 'z', min[2], max[2], settings.print_z)?;
+    }
+
+    Ok(())
+}
+
+///Check that the skirt and brim chains are inside the build volume
+pub fn check_skirt_and_brim_bounds<'a>(
+    chains: impl Iterator<Item = &'a MoveChain>,
+    settings: &Settings,
+) -> Result<(), SlicerErrors> {
+    for chain in chains {
+        for point in std::iter::once(&chain.start_point).chain(chain.moves.iter().map(|m| &m.end)) {
+            check_range(None, 'x', point.x, point.x, settings.print_x)?;
+            check_range(None, 'y', point.y, point.y, settings.print_y)?;
+        }
+    }
+
+    Ok(())
+}
+
+///Check that every move is inside the build volume, moves before the first object change aren't part of an object
+pub fn check_command_bounds(
+    commands: &[Command],
+    objects: &[ObjectDescription],
+    settings: &Settings,
+) -> Result<(), SlicerErrors> {
+    let mut name = None;
+
+    let check_point = |name: Option<&str>, point: &Coordinate<f64>| {
+        check_range(name, 'x', point.x, point.x, settings.print_x)?;
+        check_range(name, 'y', point.y, point.y, settings.print_y)
+    };
+
+    for command in commands {
+        match command {
+            Command::ChangeObject { object } => {
+                name = objects.get(*object).map(|object| object.name.as_str())
+            }
+            Command::MoveTo { end } => check_point(name, end)?,
+            Command::MoveAndExtrude { start, end, .. } | Command::Arc { start, end, .. } => {
+                check_point(name, start)?;
+                check_point(name, end)?;
+            }
+            Command::MoveAndExtrudeZ {
+                start, end, end_z, ..
+            } => {
+                check_point(name, start)?;
+                check_point(name, end)?;
+                check_range(name, 'z', *end_z, *end_z, settings.print_z)?;
+            }
+            Command::LayerChange { z } => check_range(name, 'z', *z, *z, settings.print_z)?,
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+///Return the distance the range is outside of 0 to size on the axis as an error for the named object or a move outside of an object
+fn check_range(
+    name: Option<&str>,
+    axis: char,
+    min: f64,
+    max: f64,
+    size: f64,
+) -> Result<(), SlicerErrors> {
+    let distance = (-min).max(max - size);
+
+    if distance > BOUNDS_TOLERANCE {
+        Err(match name {
+            Some(name) => SlicerErrors::ObjectOutOfBounds {
+                name: name.to_string(),
+                axis,
+                distance,
+            },
+            None => SlicerErrors::MoveOutOfBounds { axis, distance },
+        })
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn moves_outside_the_bed_are_reported() {
+        let settings = Settings::default();
+        let objects = vec![ObjectDescription {
+            name: "part".to_string(),
+            metadata: vec![],
+        }];
+        let commands = vec![
+            Command::ChangeObject { object: 0 },
+            Command::MoveTo {
+                end: Coordinate { x: 10.0, y: 10.0 },
+            },
+        ];
+        assert!(check_command_bounds(&commands, &objects, &settings).is_ok());
+
+        let commands = vec![
+            Command::ChangeObject { object: 0 },
+            Command::MoveTo {
+                end: Coordinate {
+                    x: 10.0,
+                    y: settings.print_y + 2.0,
+                },
+            },
+        ];
+        assert_eq!(
+            check_command_bounds(&commands, &objects, &settings),
+            Err(SlicerErrors::ObjectOutOfBounds {
+                name: "part".to_string(),
+                axis: 'y',
+                distance: 2.0,
+            })
+        );
+    }
+
+    #[test]
+    fn moves_outside_of_objects_are_not_named() {
+        let settings = Settings::default();
+        let outside = Coordinate { x: -1.5, y: 10.0 };
+
+        let commands = vec![Command::MoveTo { end: outside }];
+        assert_eq!(
+            check_command_bounds(&commands, &[], &settings),
+            Err(SlicerErrors::MoveOutOfBounds {
+                axis: 'x',
+                distance: 1.5,
+            })
+        );
+
+        let skirt = MoveChain {
+            start_point: Coordinate { x: 10.0, y: 10.0 },
+            moves: vec![Move {
+                end: outside,
+                move_type: MoveType::OuterPerimeter,
+                width: 0.4,
+            }],
+        };
+        assert_eq!(
+            check_skirt_and_brim_bounds([skirt].iter(), &settings),
+            Err(SlicerErrors::MoveOutOfBounds {
+                axis: 'x',
+                distance: 1.5,
+            })
+        );
+    }
+}
@@ -27,6 +27,13 @@ pub fn calculate_values(moves: &[Command], settings: &Settings) -> CalculatedVal
                 end,
                 width,
                 thickness,
+            }
+            | Command::MoveAndExtrudeZ {
+                start,
+                end,
+                width,
+                thickness,
+                ..
             } => {
                 let x_diff = end.x - start.x;
                 let y_diff = end.y - start.y;
@@ -0,0 +1,238 @@
+use crate::input::InputModel;
+use crate::repair::{connected_parts, count_inverted_triangles, edge_triangles, signed_volume};
+use crate::tower::TriangleTower;
+use gladius_shared::settings::Settings;
+use gladius_shared::types::*;
+use nalgebra::{Point3, Vector3};
+use std::collections::{HashMap, HashSet};
+
+///Tolerance used by the intersection tests
+const EPSILON: f64 = 0.000_000_1;
+
+///Intersections closer than this distance in mm to an edge are ignored as mesh noise
+const INTERSECTION_TOLERANCE: f64 = 0.001;
+
+///Check a model for problems that would stop it from printing
+pub fn check_model(model: &InputModel, settings: &Settings) -> ModelCheckReport {
+    let triangles: Vec<[usize; 3]> = model.triangles.iter().map(|tri| tri.verts).collect();
+
+    let edge_map = edge_triangles(&triangles);
+    let parts = connected_parts(&triangles, &edge_map);
+
+    let (min, max) = model.vertices.iter().fold(
+        (
+            Vertex {
+                x: f64::INFINITY,
+                y: f64::INFINITY,
+                z: f64::INFINITY,
+            },
+            Vertex {
+                x: f64::NEG_INFINITY,
+                y: f64::NEG_INFINITY,
+                z: f64::NEG_INFINITY,
+            },
+        ),
+        |(min, max), v| {
+            (
+                Vertex {
+                    x: min.x.min(v.x),
+                    y: min.y.min(v.y),
+                    z: min.z.min(v.z),
+                },
+                Vertex {
+                    x: max.x.max(v.x),
+                    y: max.y.max(v.y),
+                    z: max.z.max(v.z),
+                },
+            )
+        },
+    );
+
+    let fits_build_volume = min.x >= 0.0
+        && min.y >= 0.0
+        && min.z >= 0.0
+        && max.x <= settings.print_x
+        && max.y <= settings.print_y
+        && max.z <= settings.print_z;
+
+    ModelCheckReport {
+        name: model.name.clone(),
+        triangle_count: triangles.len(),
+        volume_count: parts.len(),
+        volume: parts
+            .iter()
+            .map(|members| signed_volume(&triangles, members, &model.vertices).abs())
+            .sum(),
+        non_manifold_edges: edge_map.values().filter(|tris| tris.len() > 2).count(),
+        open_boundary_edges: edge_map.values().filter(|tris| tris.len() == 1).count(),
+        self_intersections: count_self_intersections(&triangles, &model.vertices),
+        inverted_normals: count_inverted_triangles(&triangles, &model.vertices),
+        min,
+        max,
+        fits_build_volume,
+        tower_error: TriangleTower::from_triangles_and_vertices(
+            &model.triangles,
+            model.vertices.clone(),
+        )
+        .err(),
+    }
+}
+
+///Count the pairs of triangles that intersect, triangles sharing a vertex are not checked
+fn count_self_intersections(triangles: &[[usize; 3]], vertices: &[Vertex]) -> usize {
+    if triangles.is_empty() {
+        return 0;
+    }
+
+    let points: Vec<[Point3<f64>; 3]> = triangles
+        .iter()
+        .map(|tri| tri.map(|index| Point3::from(vertices[index])))
+        .collect();
+
+    let bounds: Vec<(Point3<f64>, Point3<f64>)> = points
+        .iter()
+        .map(|[a, b, c]| (a.inf(b).inf(c), a.sup(b).sup(c)))
+        .collect();
+
+    //Bucket the triangles into a grid sized to the average triangle so only nearby triangles are compared
+    let cell_size = (bounds
+        .iter()
+        .map(|(min, max)| (max - min).max())
+        .sum::<f64>()
+        / bounds.len() as f64)
+        .max(EPSILON);
+    let cell = |p: &Point3<f64>| {
+        (
+            (p.x / cell_size).floor() as i64,
+            (p.y / cell_size).floor() as i64,
+            (p.z / cell_size).floor() as i64,
+        )
+    };
+
+    let mut grid: HashMap<(i64, i64, i64), Vec<usize>> = HashMap::new();
+    for (index, (min, max)) in bounds.iter().enumerate() {
+        let (min_cell, max_cell) = (cell(min), cell(max));
+        for x in min_cell.0..=max_cell.0 {
+            for y in min_cell.1..=max_cell.1 {
+                for z in min_cell.2..=max_cell.2 {
+                    grid.entry((x, y, z)).or_default().push(index);
+                }
+            }
+        }
+    }
+
+    let mut intersecting = HashSet::new();
+
+    for members in grid.values() {
+        for (position, &first) in members.iter().enumerate() {
+            for &second in &members[position + 1..] {
+                let pair = (first.min(second), first.max(second));
+
+                if intersecting.contains(&pair)
+                    || triangles[first]
+                        .iter()
+                        .any(|index| triangles[second].contains(index))
+                {
+                    continue;
+                }
+
+                let (min_a, max_a) = bounds[first];
+                let (min_b, max_b) = bounds[second];
+                let overlap =
+                    (0..3).all(|axis| min_a[axis] <= max_b[axis] && min_b[axis] <= max_a[axis]);
+
+                if overlap && triangles_intersect(&points[first], &points[second]) {
+                    intersecting.insert(pair);
+                }
+            }
+        }
+    }
+
+    intersecting.len()
+}
+
+///Two triangles intersect if an edge of one passes through the other
+fn triangles_intersect(a: &[Point3<f64>; 3], b: &[Point3<f64>; 3]) -> bool {
+    let edge_crosses = |tri: &[Point3<f64>; 3], other: &[Point3<f64>; 3]| {
+        (0..3).any(|i| segment_intersects_triangle(&tri[i], &tri[(i + 1) % 3], other))
+    };
+
+    edge_crosses(a, b) || edge_crosses(b, a)
+}
+
+///Möller–Trumbore intersection of the segment from start to end with a triangle.
+/// Intersections within the tolerance of the segment ends or the triangle edges are not counted.
+fn segment_intersects_triangle(
+    start: &Point3<f64>,
+    end: &Point3<f64>,
+    tri: &[Point3<f64>; 3],
+) -> bool {
+    let direction: Vector3<f64> = end - start;
+    let edge1 = tri[1] - tri[0];
+    let edge2 = tri[2] - tri[0];
+
+    let p = direction.cross(&edge2);
+    let det = edge1.dot(&p);
+
+    //Parallel segments can't cross the triangle
+    if det.abs() < EPSILON {
+        return false;
+    }
+
+    let inv_det = 1.0 / det;
+    let s = start - tri[0];
+    let u = s.dot(&p) * inv_det;
+    if !(0.0..=1.0).contains(&u) {
+        return false;
+    }
+
+    let q = s.cross(&edge1);
+    let v = direction.dot(&q) * inv_det;
+    if v < 0.0 || u + v > 1.0 {
+        return false;
+    }
+
+    let t = edge2.dot(&q) * inv_det;
+    if !(0.0..=1.0).contains(&t) {
+        return false;
+    }
+
+    let length = direction.norm();
+    let point = start + direction * t;
+
+    let distance_to_edge = |a: &Point3<f64>, b: &Point3<f64>| {
+        let edge = b - a;
+        (point - a).cross(&edge).norm() / edge.norm()
+    };
+
+    t * length > INTERSECTION_TOLERANCE
+        && (1.0 - t) * length > INTERSECTION_TOLERANCE
+        && (0..3).all(|i| distance_to_edge(&tri[i], &tri[(i + 1) % 3]) > INTERSECTION_TOLERANCE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crossing_triangles_intersect() {
+        let flat = [
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(2.0, 0.0, 0.0),
+            Point3::new(0.0, 2.0, 0.0),
+        ];
+        let upright = [
+            Point3::new(0.5, 0.5, -1.0),
+            Point3::new(0.5, 0.5, 1.0),
+            Point3::new(3.0, 3.0, 1.0),
+        ];
+        let above = [
+            Point3::new(0.5, 0.5, 1.0),
+            Point3::new(0.5, 0.5, 2.0),
+            Point3::new(3.0, 3.0, 2.0),
+        ];
+
+        assert!(triangles_intersect(&flat, &upright));
+        assert!(!triangles_intersect(&flat, &above));
+    }
+}
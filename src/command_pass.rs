@@ -60,12 +60,8 @@ impl CommandPass for SlowDownLayerPass {
                                         non_move_time += d / current_speed;
                                     }
                                 }
-                                Command::MoveAndExtrude {
-                                    start,
-                                    end,
-                                    width: _width,
-                                    thickness: _thickness,
-                                } => {
+                                Command::MoveAndExtrude { start, end, .. }
+                                | Command::MoveAndExtrudeZ { start, end, .. } => {
                                     let x_diff = end.x - start.x;
                                     let y_diff = end.y - start.y;
                                     let d = ((x_diff * x_diff) + (y_diff * y_diff)).sqrt();
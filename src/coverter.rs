@@ -1,8 +1,10 @@
-use crate::{Command, Settings};
+use crate::{Command, ObjectDescription, Settings};
+use geo::Coordinate;
 use std::io::{BufWriter, Write};
 
 pub fn convert(
     cmds: &[Command],
+    objects: &[ObjectDescription],
     settings: Settings,
     write: &mut impl Write,
 ) -> Result<(), Box<dyn std::error::Error>> {
@@ -23,6 +25,38 @@ pub fn convert(
 
     writeln!(write_buf, "{}", start)?;
 
+    for (index, object) in objects.iter().enumerate() {
+        for (name, value) in &object.metadata {
+            writeln!(
+                write_buf,
+                "; Object {} ({}) {}: {}",
+                index,
+                comment_text(&object.name),
+                comment_text(name),
+                comment_text(value)
+            )?;
+        }
+    }
+
+    let get_extrusion =
+        |start: &Coordinate<f64>, end: &Coordinate<f64>, width: f64, thickness: f64| {
+            let x_diff = end.x - start.x;
+            let y_diff = end.y - start.y;
+            let length = ((x_diff * x_diff) + (y_diff * y_diff)).sqrt();
+
+            //let extrusion_width = width + (thickness * (1.0 - std::f64::consts::FRAC_PI_4));
+
+            let extrusion_volume = (((width - thickness) * thickness)
+                + (std::f64::consts::PI * (thickness / 2.0) * (thickness / 2.0)))
+                * length;
+            /*let extrusion_volume = width*thickness*length;*/
+
+            let filament_area =
+                (std::f64::consts::PI * settings.filament.diameter * settings.filament.diameter)
+                    / 4.0;
+            extrusion_volume / filament_area
+        };
+
     for cmd in cmds {
         match cmd {
             Command::MoveTo { end, .. } => writeln!(write_buf, "G1 X{:.5} Y{:.5}", end.x, end.y)?,
@@ -32,25 +66,26 @@ pub fn convert(
                 width,
                 thickness,
             } => {
-                let x_diff = end.x - start.x;
-                let y_diff = end.y - start.y;
-                let length = ((x_diff * x_diff) + (y_diff * y_diff)).sqrt();
-
-                //let extrusion_width = width + (thickness * (1.0 - std::f64::consts::FRAC_PI_4));
-
-                let extrusion_volume = (((width - thickness) * thickness)
-                    + (std::f64::consts::PI * (thickness / 2.0) * (thickness / 2.0)))
-                    * length;
-                /*let extrusion_volume = width*thickness*length;*/
-
-                let filament_area = (std::f64::consts::PI
-                    * settings.filament.diameter
-                    * settings.filament.diameter)
-                    / 4.0;
-                let extrude = extrusion_volume / filament_area;
+                let extrude = get_extrusion(start, end, *width, *thickness);
 
                 writeln!(write_buf, "G1 X{:.5} Y{:.5} E{:.5}", end.x, end.y, extrude)?;
             }
+            Command::MoveAndExtrudeZ {
+                start,
+                end,
+                end_z,
+                width,
+                thickness,
+            } => {
+                current_z = *end_z;
+                let extrude = get_extrusion(start, end, *width, *thickness);
+
+                writeln!(
+                    write_buf,
+                    "G1 X{:.5} Y{:.5} Z{:.5} E{:.5}",
+                    end.x, end.y, end_z, extrude
+                )?;
+            }
             Command::SetState { new_state } => {
                 match new_state.retract {
                     None => {}
@@ -151,7 +186,16 @@ pub fn convert(
                 )?;
             }
             Command::ChangeObject { object } => {
-                writeln!(write_buf, "; Change Object to {}", object)?;
+                let name = objects
+                    .get(*object)
+                    .map(|object| object.name.as_str())
+                    .unwrap_or_default();
+                writeln!(
+                    write_buf,
+                    "; Change Object to {} ({})",
+                    object,
+                    comment_text(name)
+                )?;
             }
             Command::NoAction => {
                 panic!("Converter reached a No Action Command, Optimization Failure")
@@ -169,3 +213,44 @@ pub fn convert(
 
     Ok(())
 }
+
+///Replace the control characters in text from model files so it stays inside a single comment line
+fn comment_text(text: &str) -> String {
+    text.chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn object_names_stay_in_comments() {
+        let objects = vec![ObjectDescription {
+            name: "part\nG28\r\nM104 S300".to_string(),
+            metadata: vec![("partnumber".to_string(), "A1\nG1 Z0".to_string())],
+        }];
+        let settings = Settings {
+            starting_instructions: String::new(),
+            ending_instructions: String::new(),
+            ..Settings::default()
+        };
+
+        let mut gcode = vec![];
+        convert(
+            &[Command::ChangeObject { object: 0 }],
+            &objects,
+            settings,
+            &mut gcode,
+        )
+        .unwrap();
+        let gcode = String::from_utf8(gcode).unwrap();
+
+        assert!(gcode.contains("; Change Object to 0 (part G28  M104 S300)\n"));
+        assert!(gcode.contains("; Object 0 (part G28  M104 S300) partnumber: A1 G1 Z0\n"));
+        assert!(gcode
+            .lines()
+            .all(|line| line.is_empty() || line.starts_with(';')));
+    }
+}
@@ -0,0 +1,758 @@
+use crate::input::InputModel;
+use crate::plotter::polygon_operations::PolygonOperations;
+use geo::prelude::*;
+use geo::*;
+use gladius_shared::error::SlicerErrors;
+use gladius_shared::loader::rotate_lowest_vertex_first;
+use gladius_shared::types::{Cut, IndexedTriangle, PinHoles, Vertex};
+use log::info;
+use nalgebra::{Rotation3, Unit, Vector3};
+use std::collections::HashMap;
+use std::f64::consts::PI;
+
+///Vertices closer than this distance in mm to the cut plane move the plane so no vertex lies on it
+const PLANE_EPSILON: f64 = 0.000_01;
+
+///Number of sides of the polygon used for each pin hole
+const PIN_HOLE_SIDES: usize = 16;
+
+///Minimum distance in mm between a pin hole and the edge of the cut face
+const PIN_HOLE_WALL: f64 = 1.0;
+
+///An outer loop of the cap and the holes inside it
+type Face = (Vec<usize>, Vec<Vec<usize>>);
+
+///A loop of the cap and its signed area
+type AreaLoop = (f64, Vec<usize>);
+
+///The cut plane and the directions used to flatten it into 2D
+struct Plane {
+    origin: Vector3<f64>,
+    normal: Vector3<f64>,
+    u: Vector3<f64>,
+    v: Vector3<f64>,
+}
+
+impl Plane {
+    fn distance(&self, vertex: &Vertex) -> f64 {
+        (to_vector(vertex) - self.origin).dot(&self.normal)
+    }
+
+    fn flatten(&self, vertex: &Vertex) -> [f64; 2] {
+        let offset = to_vector(vertex) - self.origin;
+        [offset.dot(&self.u), offset.dot(&self.v)]
+    }
+
+    fn unflatten(&self, [x, y]: [f64; 2], height: f64) -> Vertex {
+        to_vertex(self.origin + self.u * x + self.v * y + self.normal * height)
+    }
+}
+
+///Cut the model along the plane into the part behind it and the part in front of it, each closed with a flat cap on the plane.
+/// Both parts are laid on their cut faces and placed side by side centered where the model was, `spacing` mm apart.
+/// The plane is placed relative to the origin, the center of the bottom of the model.
+pub fn cut_model(
+    model: InputModel,
+    cut: &Cut,
+    origin: Vertex,
+    spacing: f64,
+) -> Result<(InputModel, InputModel), SlicerErrors> {
+    let normal = Vector3::from(cut.normal);
+    if normal.norm() < PLANE_EPSILON {
+        return Err(SlicerErrors::InputMisformat);
+    }
+    let normal = normal.normalize();
+
+    //Any direction in the plane works for the flattened coordinates as long as u cross v is the normal
+    let other = if normal.x.abs() < 0.9 {
+        Vector3::x()
+    } else {
+        Vector3::y()
+    };
+    let u = normal.cross(&other).normalize();
+    let mut plane = Plane {
+        origin: to_vector(&origin) + normal * cut.distance,
+        normal,
+        u,
+        v: normal.cross(&u),
+    };
+
+    //Vertices on the plane would make zero length cut edges so the plane is moved off them
+    while model
+        .vertices
+        .iter()
+        .any(|vertex| plane.distance(vertex).abs() < PLANE_EPSILON)
+    {
+        plane.origin += plane.normal * PLANE_EPSILON * 3.0;
+    }
+
+    let mut vertices = model.vertices.clone();
+    let distances: Vec<f64> = vertices
+        .iter()
+        .map(|vertex| plane.distance(vertex))
+        .collect();
+
+    //The triangles behind and in front of the plane
+    let mut parts: [Vec<[usize; 3]>; 2] = [vec![], vec![]];
+
+    //Edges of the front part's cap, the reverse of the new edges of the split triangles in front of the plane
+    let mut cap_edges: HashMap<usize, usize> = HashMap::new();
+    let mut closed = true;
+
+    let mut intersections: HashMap<(usize, usize), usize> = HashMap::new();
+    let mut get_intersection = |a: usize, b: usize, vertices: &mut Vec<Vertex>| {
+        *intersections
+            .entry((a.min(b), a.max(b)))
+            .or_insert_with(|| {
+                let t = distances[a] / (distances[a] - distances[b]);
+                let (start, end) = (to_vector(&vertices[a]), to_vector(&vertices[b]));
+                vertices.push(to_vertex(start + (end - start) * t));
+                vertices.len() - 1
+            })
+    };
+
+    for tri in &model.triangles {
+        let sides = tri.verts.map(|index| usize::from(distances[index] > 0.0));
+
+        if sides[0] == sides[1] && sides[1] == sides[2] {
+            parts[sides[0]].push(tri.verts);
+            continue;
+        }
+
+        //Rotate the triangle so the first vertex is the only one on its side
+        let lone = (0..3)
+            .find(|&index| {
+                sides[index] != sides[(index + 1) % 3] && sides[index] != sides[(index + 2) % 3]
+            })
+            .expect("One vertex is alone on its side");
+        let [a, b, c] = [0, 1, 2].map(|offset| tri.verts[(lone + offset) % 3]);
+        let ab = get_intersection(a, b, &mut vertices);
+        let ac = get_intersection(a, c, &mut vertices);
+
+        let lone_side = sides[lone];
+        parts[lone_side].push([a, ab, ac]);
+        parts[1 - lone_side].push([ab, b, c]);
+        parts[1 - lone_side].push([ab, c, ac]);
+
+        let (start, end) = if lone_side == 1 { (ac, ab) } else { (ab, ac) };
+        closed &= cap_edges.insert(start, end).is_none();
+    }
+
+    if parts.iter().any(|part| part.is_empty()) {
+        return Err(SlicerErrors::CutPlaneMissesObject { name: model.name });
+    }
+
+    let loops =
+        get_loops(cap_edges)
+            .filter(|_| closed)
+            .ok_or_else(|| SlicerErrors::CutNotClosed {
+                name: model.name.clone(),
+            })?;
+
+    let mut points: Vec<[f64; 2]> = vertices
+        .iter()
+        .map(|vertex| plane.flatten(vertex))
+        .collect();
+    let areas: Vec<f64> = loops
+        .iter()
+        .map(|ring| get_signed_area(ring, &points))
+        .collect();
+
+    //The front cap of a mesh with outward normals runs clockwise so its outer loops have negative area
+    let outward = areas.iter().sum::<f64>() < 0.0;
+    let mut faces = get_faces(loops, &areas, outward, &points);
+
+    //Pin holes are added to both caps and the pin walls to each part
+    let mut new_triangles: [Vec<[usize; 3]>; 2] = [vec![], vec![]];
+    if let Some(pins) = &cut.pins {
+        for (outer, holes) in faces.iter_mut() {
+            let centers = get_pin_centers(outer, holes, &points, pins);
+            if centers.is_empty() {
+                info!(
+                    "No room for alignment pins on part of the cut face of {}",
+                    model.name
+                );
+            }
+
+            for center in centers {
+                let hole = add_pin_hole(center, pins, &plane, &mut vertices, &mut new_triangles);
+                holes.push(hole);
+            }
+        }
+        points = vertices
+            .iter()
+            .map(|vertex| plane.flatten(vertex))
+            .collect();
+    }
+
+    //Triangles from the triangulation are counter clockwise so face along the normal
+    for (outer, holes) in &faces {
+        for [a, b, c] in triangulate(outer, holes, &points) {
+            new_triangles[0].push([a, b, c]);
+            new_triangles[1].push([a, c, b]);
+        }
+    }
+
+    //The new triangles are made for a mesh with outward normals
+    for (part, mut new_part) in parts.iter_mut().zip(new_triangles) {
+        if !outward {
+            for tri in new_part.iter_mut() {
+                tri.swap(1, 2);
+            }
+        }
+        part.extend(new_part);
+    }
+
+    //Each part is laid on its cut face, the back part's cap faces along the normal
+    let [back, front] = parts;
+    let back = create_part(&model, "part 1", &vertices, back, &plane.normal);
+    let front = create_part(&model, "part 2", &vertices, front, &-plane.normal);
+
+    let back_width = get_width(&back);
+    let front_width = get_width(&front);
+    let total_width = back_width + spacing + front_width;
+
+    let back = place_part(back, origin.x - (total_width - back_width) / 2.0, origin.y);
+    let front = place_part(
+        front,
+        origin.x + (total_width - front_width) / 2.0,
+        origin.y,
+    );
+
+    Ok((back, front))
+}
+
+///Join the edges into closed loops, none if any loop is open
+fn get_loops(mut edges: HashMap<usize, usize>) -> Option<Vec<Vec<usize>>> {
+    let mut loops = vec![];
+
+    //Loops are started from the lowest index so the result doesn't depend on the hash order
+    let mut starts: Vec<usize> = edges.keys().copied().collect();
+    starts.sort_unstable();
+
+    for start in starts {
+        let mut current = match edges.remove(&start) {
+            Some(next) => next,
+            None => continue,
+        };
+        let mut ring = vec![start];
+
+        while current != start {
+            ring.push(current);
+            current = edges.remove(&current)?;
+        }
+
+        loops.push(ring);
+    }
+
+    Some(loops)
+}
+
+///Group the loops into outer loops and the holes inside them.
+/// Outer loops have the same winding as the whole cap, each hole is in the smallest outer loop containing it
+fn get_faces(
+    loops: Vec<Vec<usize>>,
+    areas: &[f64],
+    outward: bool,
+    points: &[[f64; 2]],
+) -> Vec<Face> {
+    let (mut outers, holes): (Vec<AreaLoop>, Vec<AreaLoop>) = areas
+        .iter()
+        .copied()
+        .zip(loops)
+        .partition(|(area, _)| (*area < 0.0) == outward);
+
+    outers.sort_by(|(a, _), (b, _)| a.abs().partial_cmp(&b.abs()).unwrap());
+
+    let outer_polygons: Vec<Polygon<f64>> = outers
+        .iter()
+        .map(|(_, ring)| get_polygon(ring, points))
+        .collect();
+
+    let mut faces: Vec<Face> = outers.into_iter().map(|(_, ring)| (ring, vec![])).collect();
+
+    for (_, hole) in holes {
+        let [x, y] = points[hole[0]];
+        if let Some(index) = outer_polygons
+            .iter()
+            .position(|polygon| polygon.contains(&Point::new(x, y)))
+        {
+            faces[index].1.push(hole);
+        }
+    }
+
+    faces
+}
+
+///Centers of the pin holes for a face, two as far apart as possible if they fit or one if only one does
+fn get_pin_centers(
+    outer: &[usize],
+    holes: &[Vec<usize>],
+    points: &[[f64; 2]],
+    pins: &PinHoles,
+) -> Vec<[f64; 2]> {
+    let face = MultiPolygon(vec![Polygon::new(
+        get_polygon(outer, points).exterior().clone(),
+        holes
+            .iter()
+            .map(|hole| get_polygon(hole, points).exterior().clone())
+            .collect(),
+    )]);
+
+    //Any point left after shrinking the face has room for a pin hole around it.
+    // Holes are kept at least their depth from the edge so they don't break through walls sloping in by up to 45 degrees
+    let room = face.offset_from(-(pins.diameter / 2.0 + PIN_HOLE_WALL.max(pins.depth)));
+    if room.0.is_empty() {
+        return vec![];
+    }
+
+    let candidates: Vec<Coordinate<f64>> = room.convex_hull().exterior().0.clone();
+    let farthest = candidates
+        .iter()
+        .flat_map(|a| candidates.iter().map(move |b| (a, b)))
+        .max_by(|(a, b), (c, d)| {
+            Point(**a)
+                .euclidean_distance(&Point(**b))
+                .partial_cmp(&Point(**c).euclidean_distance(&Point(**d)))
+                .unwrap()
+        });
+
+    match farthest {
+        Some((a, b))
+            if Point(*a).euclidean_distance(&Point(*b)) > pins.diameter + PIN_HOLE_WALL =>
+        {
+            vec![[a.x, a.y], [b.x, b.y]]
+        }
+        Some((a, _)) => vec![[a.x, a.y]],
+        None => vec![],
+    }
+}
+
+///Add the walls and bottom of a pin hole into each part and return the loop of the hole in the cap
+fn add_pin_hole(
+    center: [f64; 2],
+    pins: &PinHoles,
+    plane: &Plane,
+    vertices: &mut Vec<Vertex>,
+    new_triangles: &mut [Vec<[usize; 3]>; 2],
+) -> Vec<usize> {
+    let radius = pins.diameter / 2.0;
+    let corners: Vec<[f64; 2]> = (0..PIN_HOLE_SIDES)
+        .map(|side| {
+            let angle = side as f64 * 2.0 * PI / PIN_HOLE_SIDES as f64;
+            [
+                center[0] + radius * angle.cos(),
+                center[1] + radius * angle.sin(),
+            ]
+        })
+        .collect();
+
+    let mut add_ring = |height: f64| -> Vec<usize> {
+        corners
+            .iter()
+            .map(|corner| {
+                vertices.push(plane.unflatten(*corner, height));
+                vertices.len() - 1
+            })
+            .collect()
+    };
+
+    let cap_ring = add_ring(0.0);
+    let back_ring = add_ring(-pins.depth);
+    let front_ring = add_ring(pins.depth);
+
+    //The walls face the middle of the hole and the bottom of each hole faces back towards the cap
+    let middle = to_vector(&plane.unflatten(center, 0.0));
+    let [back_triangles, front_triangles] = new_triangles;
+    for (ring, triangles, bottom_normal) in [
+        (back_ring, back_triangles, plane.normal),
+        (front_ring, front_triangles, -plane.normal),
+    ] {
+        let oriented = |tri: [usize; 3], expected: Vector3<f64>, vertices: &[Vertex]| {
+            let [a, b, c] = tri.map(|index| to_vector(&vertices[index]));
+            if (b - a).cross(&(c - a)).dot(&expected) < 0.0 {
+                [tri[0], tri[2], tri[1]]
+            } else {
+                tri
+            }
+        };
+
+        for side in 0..PIN_HOLE_SIDES {
+            let next = (side + 1) % PIN_HOLE_SIDES;
+
+            let edge_middle =
+                (to_vector(&vertices[cap_ring[side]]) + to_vector(&vertices[cap_ring[next]])) / 2.0;
+            let inward = middle - edge_middle;
+            let inward = inward - plane.normal * inward.dot(&plane.normal);
+
+            triangles.push(oriented(
+                [cap_ring[side], cap_ring[next], ring[next]],
+                inward,
+                vertices,
+            ));
+            triangles.push(oriented(
+                [cap_ring[side], ring[next], ring[side]],
+                inward,
+                vertices,
+            ));
+
+            if side != 0 && next != 0 {
+                triangles.push(oriented(
+                    [ring[0], ring[side], ring[next]],
+                    bottom_normal,
+                    vertices,
+                ));
+            }
+        }
+    }
+
+    cap_ring
+}
+
+///Triangulate a face by clipping ears after joining each hole to the outer loop with a bridge.
+/// The triangles are counter clockwise
+fn triangulate(outer: &[usize], holes: &[Vec<usize>], points: &[[f64; 2]]) -> Vec<[usize; 3]> {
+    let with_winding = |ring: &[usize], counter_clockwise: bool| -> Vec<usize> {
+        let mut ring = ring.to_vec();
+        if (get_signed_area(&ring, points) > 0.0) != counter_clockwise {
+            ring.reverse();
+        }
+        ring
+    };
+
+    let mut ring = with_winding(outer, true);
+    let mut holes: Vec<Vec<usize>> = holes.iter().map(|hole| with_winding(hole, false)).collect();
+
+    //Holes furthest right are bridged first so later bridges don't cross them
+    let max_x = |hole: &Vec<usize>| {
+        hole.iter()
+            .map(|index| points[*index][0])
+            .fold(f64::NEG_INFINITY, f64::max)
+    };
+    holes.sort_by(|a, b| max_x(b).partial_cmp(&max_x(a)).unwrap());
+
+    for hole in holes {
+        ring = bridge_hole(ring, hole, points);
+    }
+
+    let mut triangles = vec![];
+    let mut start = 0;
+
+    while ring.len() > 3 {
+        let len = ring.len();
+        let corner = |index: usize| {
+            (
+                ring[(index + len - 1) % len],
+                ring[index],
+                ring[(index + 1) % len],
+            )
+        };
+        let is_ear = |index: usize| {
+            let (a, b, c) = corner(index);
+            cross(points[a], points[b], points[c]) > 0.0
+                && !ring.iter().any(|&other| {
+                    other != a
+                        && other != b
+                        && other != c
+                        && in_triangle(points[other], points[a], points[b], points[c])
+                })
+        };
+
+        //Rounding can leave no ears so the most convex corner is clipped instead
+        let ear = (0..len)
+            .map(|offset| (start + offset) % len)
+            .find(|index| is_ear(*index))
+            .unwrap_or_else(|| {
+                (0..len)
+                    .max_by(|a, b| {
+                        let (a0, a1, a2) = corner(*a);
+                        let (b0, b1, b2) = corner(*b);
+                        cross(points[a0], points[a1], points[a2])
+                            .partial_cmp(&cross(points[b0], points[b1], points[b2]))
+                            .unwrap()
+                    })
+                    .expect("Ring has corners")
+            });
+
+        let (a, b, c) = corner(ear);
+        triangles.push([a, b, c]);
+        ring.remove(ear);
+        start = ear.saturating_sub(1);
+    }
+
+    triangles.push([ring[0], ring[1], ring[2]]);
+    triangles
+}
+
+///Join the hole into the ring from its rightmost point to a visible point of the ring
+fn bridge_hole(ring: Vec<usize>, hole: Vec<usize>, points: &[[f64; 2]]) -> Vec<usize> {
+    let (hole_start, &hole_point) = hole
+        .iter()
+        .enumerate()
+        .max_by(|(_, a), (_, b)| points[**a][0].partial_cmp(&points[**b][0]).unwrap())
+        .expect("Hole has points");
+    let m = points[hole_point];
+    let len = ring.len();
+
+    //The closest edge hit by a ray from the hole to the right, the end of the edge furthest right is the candidate
+    let mut hit: Option<(f64, usize)> = None;
+    for index in 0..len {
+        let a = points[ring[index]];
+        let b = points[ring[(index + 1) % len]];
+
+        if (a[1] - m[1]) * (b[1] - m[1]) <= 0.0 && a[1] != b[1] {
+            let x = a[0] + (m[1] - a[1]) / (b[1] - a[1]) * (b[0] - a[0]);
+
+            if x >= m[0] && !matches!(hit, Some((hit_x, _)) if hit_x <= x) {
+                let candidate = if a[0] > b[0] {
+                    index
+                } else {
+                    (index + 1) % len
+                };
+                hit = Some((x, candidate));
+            }
+        }
+    }
+
+    let bridge = match hit {
+        Some((x, mut candidate)) => {
+            //Points inside the triangle between the hole, the hit and the candidate would block the bridge, use the one closest to the ray
+            let hit_point = [x, m[1]];
+            let candidate_point = points[ring[candidate]];
+            let mut best_tan = f64::INFINITY;
+
+            for (index, &point_index) in ring.iter().enumerate() {
+                let p = points[point_index];
+                let inside = in_triangle(p, m, hit_point, candidate_point)
+                    || in_triangle(p, m, candidate_point, hit_point);
+
+                if index != candidate && p[0] > m[0] && inside {
+                    let tan = (p[1] - m[1]).abs() / (p[0] - m[0]);
+                    if tan < best_tan {
+                        best_tan = tan;
+                        candidate = index;
+                    }
+                }
+            }
+            candidate
+        }
+        //The hole isn't inside the ring, bridge to the closest point
+        None => (0..len)
+            .min_by(|a, b| {
+                distance(points[ring[*a]], m)
+                    .partial_cmp(&distance(points[ring[*b]], m))
+                    .unwrap()
+            })
+            .expect("Ring has points"),
+    };
+
+    let mut bridged = ring[..=bridge].to_vec();
+    bridged.extend(hole[hole_start..].iter().chain(hole[..=hole_start].iter()));
+    bridged.extend(ring[bridge..].iter());
+    bridged
+}
+
+///Copy the triangles into a new model rotated so the cap normal points down
+fn create_part(
+    model: &InputModel,
+    suffix: &str,
+    vertices: &[Vertex],
+    triangles: Vec<[usize; 3]>,
+    cap_normal: &Vector3<f64>,
+) -> InputModel {
+    //Normals pointing straight up have no unique rotation down so flip around x
+    let rotation = Rotation3::rotation_between(cap_normal, &-Vector3::z())
+        .unwrap_or_else(|| Rotation3::from_axis_angle(&Unit::new_normalize(Vector3::x()), PI));
+
+    let mut new_indices: HashMap<usize, usize> = HashMap::new();
+    let mut part_vertices = vec![];
+    let triangles: Vec<[usize; 3]> = triangles
+        .into_iter()
+        .map(|tri| {
+            tri.map(|index| {
+                *new_indices.entry(index).or_insert_with(|| {
+                    part_vertices.push(to_vertex(rotation * to_vector(&vertices[index])));
+                    part_vertices.len() - 1
+                })
+            })
+        })
+        .collect();
+
+    let triangles = triangles
+        .into_iter()
+        .map(|verts| rotate_lowest_vertex_first(IndexedTriangle { verts }, &part_vertices))
+        .collect();
+
+    InputModel {
+        name: format!("{} {}", model.name, suffix),
+        metadata: model.metadata.clone(),
+        vertices: part_vertices,
+        triangles,
+        copies: model.copies.clone(),
+        settings: model.settings.clone(),
+        volume: model.volume.clone(),
+    }
+}
+
+///Move the part so it is centered on x and y and rests on the bed
+fn place_part(mut part: InputModel, x: f64, y: f64) -> InputModel {
+    let (min, max) = get_bounds(&part);
+    let offset = Vertex {
+        x: x - (min.x + max.x) / 2.0,
+        y: y - (min.y + max.y) / 2.0,
+        z: -min.z,
+    };
+
+    for vertex in part.vertices.iter_mut() {
+        vertex.x += offset.x;
+        vertex.y += offset.y;
+        vertex.z += offset.z;
+    }
+
+    part
+}
+
+fn get_width(part: &InputModel) -> f64 {
+    let (min, max) = get_bounds(part);
+    max.x - min.x
+}
+
+fn get_bounds(part: &InputModel) -> (Vector3<f64>, Vector3<f64>) {
+    part.vertices.iter().fold(
+        (
+            Vector3::repeat(f64::INFINITY),
+            Vector3::repeat(f64::NEG_INFINITY),
+        ),
+        |(min, max), vertex| {
+            let vertex = to_vector(vertex);
+            (min.inf(&vertex), max.sup(&vertex))
+        },
+    )
+}
+
+fn get_polygon(ring: &[usize], points: &[[f64; 2]]) -> Polygon<f64> {
+    Polygon::new(
+        LineString::from(
+            ring.iter()
+                .map(|index| (points[*index][0], points[*index][1]))
+                .collect::<Vec<(f64, f64)>>(),
+        ),
+        vec![],
+    )
+}
+
+fn get_signed_area(ring: &[usize], points: &[[f64; 2]]) -> f64 {
+    ring.iter()
+        .zip(ring.iter().cycle().skip(1))
+        .map(|(a, b)| points[*a][0] * points[*b][1] - points[*b][0] * points[*a][1])
+        .sum::<f64>()
+        / 2.0
+}
+
+fn cross(o: [f64; 2], a: [f64; 2], b: [f64; 2]) -> f64 {
+    (a[0] - o[0]) * (b[1] - o[1]) - (a[1] - o[1]) * (b[0] - o[0])
+}
+
+///Whether the point is inside or on the edge of the counter clockwise triangle
+fn in_triangle(p: [f64; 2], a: [f64; 2], b: [f64; 2], c: [f64; 2]) -> bool {
+    cross(a, b, p) >= 0.0 && cross(b, c, p) >= 0.0 && cross(c, a, p) >= 0.0
+}
+
+fn distance(a: [f64; 2], b: [f64; 2]) -> f64 {
+    ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2)).sqrt()
+}
+
+fn to_vector(vertex: &Vertex) -> Vector3<f64> {
+    Vector3::new(vertex.x, vertex.y, vertex.z)
+}
+
+fn to_vertex(vector: Vector3<f64>) -> Vertex {
+    Vertex {
+        x: vector.x,
+        y: vector.y,
+        z: vector.z,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::check::check_model;
+    use gladius_shared::settings::Settings;
+
+    fn assert_closed(part: &InputModel) -> f64 {
+        let report = check_model(part, &Settings::default());
+        assert_eq!(report.open_boundary_edges, 0);
+        assert_eq!(report.non_manifold_edges, 0);
+        assert_eq!(report.inverted_normals, 0);
+        assert!(report.tower_error.is_none());
+        assert!(report.min.z.abs() < 0.000_1);
+        report.volume
+    }
+
+    #[test]
+    fn cube_is_cut_into_two_closed_parts() {
+        let cut = Cut {
+            normal: [0.0, 0.0, 1.0],
+            distance: 5.0,
+            pins: None,
+        };
+        let origin = Vertex {
+            x: 10.0,
+            y: 10.0,
+            z: 0.0,
+        };
+
+        let (back, front) = cut_model(
+            InputModel::cuboid("cube", [0.0; 3], [20.0; 3]),
+            &cut,
+            origin,
+            5.0,
+        )
+        .unwrap();
+
+        assert!((assert_closed(&back) - 20.0 * 20.0 * 5.0).abs() < 0.01);
+        assert!((assert_closed(&front) - 20.0 * 20.0 * 15.0).abs() < 0.01);
+        assert_eq!(back.name, "cube part 1");
+
+        //The back part is flipped onto its cut face and both are placed side by side
+        let (back_min, back_max) = get_bounds(&back);
+        let (front_min, front_max) = get_bounds(&front);
+        assert!((back_max.z - 5.0).abs() < 0.000_1);
+        assert!((front_max.z - 15.0).abs() < 0.000_1);
+        assert!((front_min.x - back_max.x - 5.0).abs() < 0.000_1);
+        assert!(((back_min.x + front_max.x) / 2.0 - 10.0).abs() < 0.000_1);
+    }
+
+    #[test]
+    fn pin_holes_are_cut_into_both_faces() {
+        let pins = PinHoles {
+            diameter: 3.0,
+            depth: 4.0,
+        };
+        let cut = Cut {
+            normal: [1.0, 0.0, 1.0],
+            distance: 12.0,
+            pins: Some(pins),
+        };
+        let origin = Vertex {
+            x: 10.0,
+            y: 10.0,
+            z: 0.0,
+        };
+
+        let (back, front) = cut_model(
+            InputModel::cuboid("cube", [0.0; 3], [20.0; 3]),
+            &cut,
+            origin,
+            5.0,
+        )
+        .unwrap();
+
+        //Two pin holes in each part
+        let pin_area =
+            PIN_HOLE_SIDES as f64 / 2.0 * 1.5 * 1.5 * (2.0 * PI / PIN_HOLE_SIDES as f64).sin();
+        let removed = 20.0 * 20.0 * 20.0 - assert_closed(&back) - assert_closed(&front);
+        assert!((removed - pin_area * 4.0 * 4.0).abs() < 0.01);
+    }
+}
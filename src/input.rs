@@ -1,46 +1,250 @@
+use crate::arrange::{arrange_models, ArrangeGroup};
+use crate::cut::cut_model;
+use crate::orient::get_auto_orient_transform;
 use crate::utils::show_error_message;
 use crate::*;
+use std::io::Read;
 use std::path::PathBuf;
 use std::str::FromStr;
 
+///Input path that reads the model from stdin
+const STDIN_PATH: &str = "-";
+
+///A model with its input transform applied, ready to be made into a tower
+pub struct InputModel {
+    ///Name of the model, used in commands and errors
+    pub name: String,
+
+    ///Metadata of the model from the model file as name and value pairs
+    pub metadata: Vec<(String, String)>,
+
+    ///The transformed vertices of the model
+    pub vertices: Vec<Vertex>,
+
+    ///The triangles of the model
+    pub triangles: Vec<IndexedTriangle>,
+
+    ///Offsets in x and y of the additional copies of the model
+    pub copies: Vec<Coordinate<f64>>,
+
+    ///The global settings with any overrides for the model applied
+    pub settings: Settings,
+
+    ///How the model is used in the print
+    pub volume: VolumeType,
+}
+
+#[cfg(test)]
+impl InputModel {
+    ///Load the first model in the file without a transform as a part with the default settings
+    pub fn from_test_file(path: &str) -> Self {
+        let (vertices, triangles) = LoaderRegistry::default()
+            .get_loader(path)
+            .unwrap()
+            .load(path)
+            .unwrap()
+            .remove(0);
+
+        Self::from_test_mesh(path, vertices, triangles)
+    }
+
+    ///An axis aligned box from the min to the max corner as a part with the default settings
+    pub fn cuboid(name: &str, min: [f64; 3], max: [f64; 3]) -> Self {
+        let vertices: Vec<Vertex> = (0..8)
+            .map(|corner| Vertex {
+                x: if corner & 1 == 0 { min[0] } else { max[0] },
+                y: if corner & 2 == 0 { min[1] } else { max[1] },
+                z: if corner & 4 == 0 { min[2] } else { max[2] },
+            })
+            .collect();
+        let faces = [
+            [0, 2, 3],
+            [0, 3, 1],
+            [4, 5, 7],
+            [4, 7, 6],
+            [0, 1, 5],
+            [0, 5, 4],
+            [2, 6, 7],
+            [2, 7, 3],
+            [0, 4, 6],
+            [0, 6, 2],
+            [1, 3, 7],
+            [1, 7, 5],
+        ];
+        let triangles = faces
+            .iter()
+            .map(|&verts| rotate_lowest_vertex_first(IndexedTriangle { verts }, &vertices))
+            .collect();
+
+        Self::from_test_mesh(name, vertices, triangles)
+    }
+
+    fn from_test_mesh(name: &str, vertices: Vec<Vertex>, triangles: Vec<IndexedTriangle>) -> Self {
+        InputModel {
+            name: name.to_string(),
+            metadata: vec![],
+            vertices,
+            triangles,
+            copies: vec![],
+            settings: Settings::default(),
+            volume: VolumeType::Part,
+        }
+    }
+}
+
 pub fn files_input(
     settings_path: Option<&str>,
     input: Option<Vec<String>>,
-) -> Result<(Vec<(Vec<Vertex>, Vec<IndexedTriangle>)>, Settings), SlicerErrors> {
+    loaders: &LoaderRegistry,
+) -> Result<(Vec<InputModel>, Settings), SlicerErrors> {
+    let objects: Vec<InputObject> = input
+        .ok_or(SlicerErrors::NoInputProvided)?
+        .iter()
+        .map(|value| {
+            if value == STDIN_PATH {
+                Ok(InputObject::Auto(STDIN_PATH.to_string()))
+            } else {
+                deser_hjson::from_str(value).map_err(|_| SlicerErrors::InputMisformat)
+            }
+        })
+        .collect::<Result<_, _>>()?;
+
+    //Stdin can only be read once so it is shared between all inputs using it
+    let stdin_contents = if objects
+        .iter()
+        .any(|object| object.get_model_path() == STDIN_PATH)
+    {
+        let mut contents = vec![];
+        std::io::stdin().read_to_end(&mut contents).map_err(|_| {
+            SlicerErrors::ObjectFileNotFound {
+                filepath: STDIN_PATH.to_string(),
+            }
+        })?;
+        Some(contents)
+    } else {
+        None
+    };
+
+    //Settings embedded in the models are used for anything the settings file doesn't set
+    let embedded_settings =
+        objects
+            .iter()
+            .try_fold(PartialSettings::default(), |settings, object| {
+                let model_path = object.get_model_path();
+                let embedded = match &stdin_contents {
+                    Some(contents) if model_path == STDIN_PATH => {
+                        get_stdin_loader(loaders, contents)?.load_settings_from_bytes(contents)?
+                    }
+                    _ => loaders.get_loader(model_path)?.load_settings(model_path)?,
+                };
+
+                Ok(match embedded {
+                    Some(embedded) => {
+                        info!("Using settings embedded in {}", model_path);
+                        settings.combine(embedded)
+                    }
+                    None => settings,
+                })
+            })?;
+
     let settings: Settings = {
         if let Some(str) = settings_path {
-            load_settings(str)
+            load_settings(str, embedded_settings)
         } else {
-            Ok(Settings::default())
+            embedded_settings.get_settings_with_fallback(Settings::default().into())
         }
     }?;
 
     info!("Loading Input");
 
-    let converted_inputs: Vec<(Vec<Vertex>, Vec<IndexedTriangle>)> = input
-        .ok_or(SlicerErrors::NoInputProvided)?
-        .iter()
-        .try_fold(vec![], |mut vec, value| {
-            let object: InputObject =
-                deser_hjson::from_str(value).map_err(|_| SlicerErrors::InputMisformat)?;
+    //Models from each input that is placed automatically or has copies placed automatically
+    let mut arrange_groups = vec![];
+
+    let mut converted_inputs: Vec<InputModel> =
+        objects.into_iter().try_fold(vec![], |mut vec, object| {
             let model_path = Path::new(object.get_model_path());
+            let (volume, cut) = match &object {
+                InputObject::Transformed { volume, cut, .. } => (volume.clone(), cut.clone()),
+                _ => (VolumeType::Part, None),
+            };
 
-            // Calling .unwrap() is safe here because "INPUT" is required (if "INPUT" wasn't
-            // required we could have used an 'if let' to conditionally get the value)
-            debug!("Using input file: {:?}", model_path);
+            //Volumes that aren't printed stay where they are placed
+            let arranged = matches!(volume, VolumeType::Part)
+                && matches!(
+                    object,
+                    InputObject::Auto(_)
+                        | InputObject::Transformed {
+                            translation: None,
+                            copies: None,
+                            ..
+                        }
+                        | InputObject::Transformed {
+                            translation: None,
+                            copies: Some(Copies::Count(_)),
+                            ..
+                        }
+                );
+
+            //Translated models stay in place and only their counted copies are arranged around them
+            let fixed = matches!(volume, VolumeType::Part)
+                && matches!(
+                    object,
+                    InputObject::Transformed {
+                        translation: Some(_),
+                        copies: Some(Copies::Count(_)),
+                        ..
+                    }
+                );
 
-            let extension = model_path
-                .extension()
-                .and_then(OsStr::to_str)
-                .expect("File Parse Issue");
+            //Copies are offset from the first placement, counted copies are placed when arranging
+            let (placement, copies) = match &object {
+                InputObject::Transformed {
+                    copies: Some(Copies::Count(count)),
+                    ..
+                } => (
+                    [0.0, 0.0],
+                    vec![Coordinate { x: 0.0, y: 0.0 }; count.saturating_sub(1)],
+                ),
+                InputObject::Transformed {
+                    copies: Some(Copies::Placements(placements)),
+                    ..
+                } => match placements.split_first() {
+                    Some((&[x, y], rest)) => (
+                        [x, y],
+                        rest.iter()
+                            .map(|[copy_x, copy_y]| Coordinate {
+                                x: copy_x - x,
+                                y: copy_y - y,
+                            })
+                            .collect(),
+                    ),
+                    None => ([0.0, 0.0], vec![]),
+                },
+                _ => ([0.0, 0.0], vec![]),
+            };
 
-            let loader: &dyn Loader = match extension.to_lowercase().as_str() {
-                "stl" => &STLLoader {},
-                "3mf" => &ThreeMFLoader {},
-                _ => panic!("File Format {} not supported", extension),
+            //Overrides for the model are layered over the global settings
+            let model_settings = match &object {
+                InputObject::Transformed {
+                    settings: Some(overrides),
+                    ..
+                } => PartialSettings::clone(overrides)
+                    .get_settings_with_fallback(settings.clone().into())?,
+                _ => settings.clone(),
             };
 
-            let models = match loader.load(model_path.to_str().unwrap()) {
+            debug!("Using input file: {:?}", model_path);
+
+            let loaded = match &stdin_contents {
+                Some(contents) if object.get_model_path() == STDIN_PATH => {
+                    get_stdin_loader(loaders, contents)?.load_from_bytes_with_metadata(contents)
+                }
+                _ => loaders
+                    .get_loader(object.get_model_path())?
+                    .load_with_metadata(object.get_model_path()),
+            };
+
+            let models = match loaded {
                 Ok(v) => v,
                 Err(err) => {
                     show_error_message(err);
@@ -48,6 +252,17 @@ pub fn files_input(
                 }
             };
 
+            //Unnamed models are named after the file they came from
+            let file_name = if object.get_model_path() == STDIN_PATH {
+                "stdin".to_string()
+            } else {
+                model_path
+                    .file_stem()
+                    .and_then(OsStr::to_str)
+                    .unwrap_or_default()
+                    .to_string()
+            };
+
             let (x, y) = match object {
                 InputObject::AutoTranslate(_, x, y) => (x, y),
                 _ => (0.0, 0.0),
@@ -57,50 +272,192 @@ pub fn files_input(
                 InputObject::Raw(_, transform) => transform,
                 InputObject::Auto(_) | InputObject::AutoTranslate(_, _, _) => {
                     let (min_x, max_x, min_y, max_y, min_z) =
-                        models.iter().flat_map(|(v, _t)| v.iter()).fold(
-                            (
-                                f64::INFINITY,
-                                f64::NEG_INFINITY,
-                                f64::INFINITY,
-                                f64::NEG_INFINITY,
-                                f64::INFINITY,
-                            ),
-                            |a, b| {
-                                (
-                                    a.0.min(b.x),
-                                    a.1.max(b.x),
-                                    a.2.min(b.y),
-                                    a.3.max(b.y),
-                                    a.4.min(b.z),
-                                )
-                            },
-                        );
+                        get_bounds(models.iter().flat_map(|(_n, v, _t)| v.iter().copied()));
                     Transform::new_translation_transform(
                         (x + settings.print_x - (max_x + min_x)) / 2.,
                         (y + settings.print_y - (max_y + min_y)) / 2.,
                         -min_z,
                     )
                 }
+                InputObject::Transformed {
+                    scale,
+                    rotation,
+                    mirror,
+                    translation,
+                    auto_orient,
+                    ..
+                } => {
+                    let [scale_x, scale_y, scale_z] = match scale {
+                        None => [1.0; 3],
+                        Some(Scale::Uniform(scale)) => [scale; 3],
+                        Some(Scale::PerAxis(scale)) => scale,
+                    };
+                    let [mirror_x, mirror_y, mirror_z] =
+                        mirror
+                            .unwrap_or_default()
+                            .map(|m| if m { -1.0 } else { 1.0 });
+                    let [rotation_x, rotation_y, rotation_z] = rotation.unwrap_or_default();
+                    let [translation_x, translation_y, translation_z] =
+                        translation.unwrap_or_default();
+
+                    let mut model_transform =
+                        &Transform::new_rotation_transform(rotation_x, rotation_y, rotation_z)
+                            * &Transform::new_scale_transform(
+                                scale_x * mirror_x,
+                                scale_y * mirror_y,
+                                scale_z * mirror_z,
+                            );
+
+                    if auto_orient {
+                        let orient_transform = get_auto_orient_transform(
+                            models.iter().map(|(_n, v, t)| (v.as_slice(), t.as_slice())),
+                            &model_transform,
+                            &settings,
+                        );
+                        model_transform = &orient_transform * &model_transform;
+                    }
+
+                    //Center and drop the model after it has been transformed
+                    let (min_x, max_x, min_y, max_y, min_z) = get_bounds(
+                        models
+                            .iter()
+                            .flat_map(|(_n, v, _t)| v.iter().map(|v| &model_transform * *v)),
+                    );
+
+                    &Transform::new_translation_transform(
+                        translation_x + placement[0] + (settings.print_x - (max_x + min_x)) / 2.,
+                        translation_y + placement[1] + (settings.print_y - (max_y + min_y)) / 2.,
+                        translation_z - min_z,
+                    ) * &model_transform
+                }
             };
 
             let trans_str = serde_json::to_string(&transform).unwrap();
 
             debug!("Using Transform {}", trans_str);
 
-            vec.extend(models.into_iter().map(move |(mut v, t)| {
-                for vert in v.iter_mut() {
-                    *vert = &transform * *vert;
+            let model_count = models.len();
+            let mirrored = transform.is_mirroring();
+            let first_model = vec.len();
+
+            vec.extend(
+                models
+                    .into_iter()
+                    .enumerate()
+                    .map(move |(index, (metadata, mut v, t))| {
+                        for vert in v.iter_mut() {
+                            *vert = &transform * *vert;
+                        }
+
+                        //The lowest vertex must be first after rotating and the winding reversed after mirroring
+                        let t = t
+                            .into_iter()
+                            .map(|mut tri| {
+                                if mirrored {
+                                    tri.verts.swap(1, 2);
+                                }
+                                rotate_lowest_vertex_first(tri, &v)
+                            })
+                            .collect();
+
+                        let name = metadata.name.unwrap_or_else(|| {
+                            if model_count == 1 {
+                                file_name.clone()
+                            } else {
+                                format!("{} {}", file_name, index)
+                            }
+                        });
+
+                        InputModel {
+                            name,
+                            metadata: metadata.values,
+                            vertices: v,
+                            triangles: t,
+                            copies: copies.clone(),
+                            settings: model_settings.clone(),
+                            volume: volume.clone(),
+                        }
+                    }),
+            );
+
+            //Cut models are replaced by their parts which are arranged separately
+            if let Some(cut) = &cut {
+                let (min_x, max_x, min_y, max_y, min_z) = get_bounds(
+                    vec[first_model..]
+                        .iter()
+                        .flat_map(|model| model.vertices.iter().copied()),
+                );
+                let origin = Vertex {
+                    x: (min_x + max_x) / 2.0,
+                    y: (min_y + max_y) / 2.0,
+                    z: min_z,
+                };
+                let cut_models = vec
+                    .drain(first_model..)
+                    .map(|model| cut_model(model, cut, origin, settings.arrange_spacing))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                for (back, front) in cut_models {
+                    vec.push(back);
+                    vec.push(front);
                 }
 
-                (v, t)
-            }));
+                if arranged || fixed {
+                    arrange_groups.extend((first_model..vec.len()).map(|index| ArrangeGroup {
+                        models: index..index + 1,
+                        fixed,
+                    }));
+                }
+            } else if arranged || fixed {
+                arrange_groups.push(ArrangeGroup {
+                    models: first_model..vec.len(),
+                    fixed,
+                });
+            }
 
             Ok(vec)
         })?;
+
+    arrange_models(&mut converted_inputs, &arrange_groups, &settings)?;
+
     Ok((converted_inputs, settings))
 }
 
-fn load_settings(filepath: &str) -> Result<Settings, SlicerErrors> {
+///Get the loader for the contents read from stdin using only the contents as there is no file extension
+fn get_stdin_loader<'a>(
+    loaders: &'a LoaderRegistry,
+    contents: &[u8],
+) -> Result<&'a dyn Loader, SlicerErrors> {
+    loaders
+        .get_loader_from_contents(contents)
+        .ok_or(SlicerErrors::UnknownFileFormat {
+            filepath: STDIN_PATH.to_string(),
+        })
+}
+
+///Get the minimum and maximum x and y and the minimum z of the vertices
+fn get_bounds(vertices: impl Iterator<Item = Vertex>) -> (f64, f64, f64, f64, f64) {
+    vertices.fold(
+        (
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+            f64::INFINITY,
+        ),
+        |a, b| {
+            (
+                a.0.min(b.x),
+                a.1.max(b.x),
+                a.2.min(b.y),
+                a.3.max(b.y),
+                a.4.min(b.z),
+            )
+        },
+    )
+}
+
+fn load_settings(filepath: &str, fallback: PartialSettings) -> Result<Settings, SlicerErrors> {
     let settings_data =
         std::fs::read_to_string(filepath).map_err(|_| SlicerErrors::SettingsFileNotFound {
             filepath: filepath.to_string(),
@@ -118,10 +475,129 @@ fn load_settings(filepath: &str) -> Result<Settings, SlicerErrors> {
 
     std::env::set_current_dir(&path).expect("Path checked before");
 
-    let settings = partial_settings.get_settings()?;
+    let settings = partial_settings.get_settings_with_fallback(fallback)?;
 
     //reset path
     std::env::set_current_dir(current_path).expect("Path checked before");
 
     Ok(settings)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::slicing::slice_test_models;
+
+    ///A tetrahedron 10mm in x, 20mm in y and 5mm in z with outward facing triangles
+    const TETRAHEDRON: &str = "solid tetrahedron
+facet normal 0 0 -1
+outer loop
+vertex 0 0 0
+vertex 0 20 0
+vertex 10 0 0
+endloop
+endfacet
+facet normal 0 -1 0
+outer loop
+vertex 0 0 0
+vertex 10 0 0
+vertex 0 0 5
+endloop
+endfacet
+facet normal -1 0 0
+outer loop
+vertex 0 0 0
+vertex 0 0 5
+vertex 0 20 0
+endloop
+endfacet
+facet normal 1 1 1
+outer loop
+vertex 10 0 0
+vertex 0 20 0
+vertex 0 0 5
+endloop
+endfacet
+endsolid tetrahedron
+";
+
+    fn load_transformed(path: &str, transform: &str) -> InputModel {
+        let input = format!(r#"{{"Transformed":{{"path":{:?},{}}}}}"#, path, transform);
+
+        files_input(None, Some(vec![input]), &LoaderRegistry::default())
+            .unwrap()
+            .0
+            .remove(0)
+    }
+
+    #[test]
+    fn scale_and_mirror_are_applied_before_rotation() {
+        let path = std::env::temp_dir().join("gladius_input_tetrahedron.stl");
+        std::fs::write(&path, TETRAHEDRON).unwrap();
+        let path = path.to_str().unwrap();
+
+        //Scaling x then rotating 90 degrees around z turns the 30mm width into the depth
+        let model = load_transformed(path, r#""scale":[3,1,1],"rotation":[0,0,90]"#);
+        let (min_x, max_x, min_y, max_y, min_z) = get_bounds(model.vertices.iter().copied());
+        assert!((max_x - min_x - 20.0).abs() < 0.000_001);
+        assert!((max_y - min_y - 30.0).abs() < 0.000_001);
+        assert_eq!(min_z, 0.0);
+
+        //Triangles face away from the center of the tetrahedron with and without mirroring
+        for transform in [
+            r#""mirror":[false,false,false]"#,
+            r#""mirror":[true,false,false]"#,
+        ] {
+            let model = load_transformed(path, transform);
+            let center = model
+                .vertices
+                .iter()
+                .fold(Vertex::default(), |a, v| Vertex {
+                    x: a.x + v.x / 4.0,
+                    y: a.y + v.y / 4.0,
+                    z: a.z + v.z / 4.0,
+                });
+
+            for triangle in &model.triangles {
+                let [a, b, c] = triangle.verts.map(|index| model.vertices[index]);
+                let (u, v) = (
+                    [b.x - a.x, b.y - a.y, b.z - a.z],
+                    [c.x - a.x, c.y - a.y, c.z - a.z],
+                );
+                let normal = [
+                    u[1] * v[2] - u[2] * v[1],
+                    u[2] * v[0] - u[0] * v[2],
+                    u[0] * v[1] - u[1] * v[0],
+                ];
+                let outward = [a.x - center.x, a.y - center.y, a.z - center.z];
+
+                assert!(
+                    normal[0] * outward[0] + normal[1] * outward[1] + normal[2] * outward[2] > 0.0
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn overrides_apply_to_their_object() {
+        let input = vec![
+            r#"{"Transformed":{"path":"test_3D_models/ring.stl","settings":{"number_of_perimeters":5,"infill_percentage":0.5}}}"#.to_string(),
+            r#"{"Transformed":{"path":"test_3D_models/ring.stl","settings":{"number_of_perimeters":1,"infill_percentage":0.1}}}"#.to_string(),
+        ];
+
+        let (models, settings) =
+            files_input(None, Some(input), &LoaderRegistry::default()).unwrap();
+        let (_, objects) = slice_test_models(&models);
+
+        assert_eq!(settings.number_of_perimeters, 3);
+        for (object, (perimeters, infill)) in objects.iter().zip([(5, 0.5), (1, 0.1)]) {
+            assert_eq!(object.settings.number_of_perimeters, perimeters);
+            assert_eq!(object.settings.infill_percentage, infill);
+            assert!(!object.layers.is_empty());
+            assert!(object
+                .layers
+                .iter()
+                .all(|slice| slice.layer_settings.infill_percentage == infill));
+        }
+    }
+}
@@ -13,14 +13,19 @@ use std::fs::File;
 use std::ffi::OsStr;
 use std::path::Path;
 
+use crate::bounds::{check_command_bounds, check_model_bounds, check_skirt_and_brim_bounds};
 use crate::calculation::calculate_values;
+use crate::check::check_model;
 use crate::command_pass::{CommandPass, OptimizePass, SlowDownLayerPass};
 use crate::coverter::*;
-use crate::input::files_input;
+use crate::input::{files_input, InputModel};
 use crate::plotter::polygon_operations::PolygonOperations;
+use crate::repair::repair_model;
+use crate::sequential::order_objects_for_sequential_printing;
 use crate::slice_pass::*;
 use crate::slicing::*;
 use crate::utils::{display_state_update, send_error_message, show_error_message};
+use crate::volumes::apply_volumes;
 use gladius_shared::error::SlicerErrors;
 use gladius_shared::messages::Message;
 use itertools::Itertools;
@@ -31,16 +36,25 @@ use simple_logger::SimpleLogger;
 use std::collections::HashMap;
 use std::io::BufWriter;
 
+mod adaptive;
+mod arrange;
+mod bounds;
 mod calculation;
+mod check;
 mod command_pass;
 mod coverter;
+mod cut;
 mod input;
 mod optimizer;
+mod orient;
 mod plotter;
+mod repair;
+mod sequential;
 mod slice_pass;
 mod slicing;
 mod tower;
 mod utils;
+mod volumes;
 
 fn main() {
     // The YAML file is found relative to the current file, similar to how modules are found
@@ -88,27 +102,88 @@ fn main() {
     }
 
     display_state_update("Loading Inputs", send_messages);
-    let (models, settings) = handle_err_or_return(
+    let (mut models, settings) = handle_err_or_return(
         files_input(
             matches.value_of("SETTINGS"),
             matches
                 .values_of("INPUT")
                 .map(|values| values.map(|v| v.to_string()).collect()),
+            &LoaderRegistry::default(),
         ),
         send_messages,
     );
 
+    if let Some(repair_settings) = &settings.repair {
+        display_state_update("Repairing Models", send_messages);
+
+        for model in models.iter_mut() {
+            let report = repair_model(model, repair_settings);
+
+            if report.has_changes() {
+                display_state_update(
+                    &format!("Repaired \"{}\": {}", model.name, report),
+                    send_messages,
+                );
+            }
+        }
+    }
+
+    if matches.is_present("CHECK") {
+        display_state_update("Checking Models", send_messages);
+
+        let reports: Vec<ModelCheckReport> = models
+            .iter()
+            .map(|model| check_model(model, &model.settings))
+            .collect();
+        let printable = reports.iter().all(|report| report.is_printable());
+
+        if send_messages {
+            let message = Message::CheckReport(reports);
+            bincode::serialize_into(BufWriter::new(std::io::stdout()), &message).unwrap();
+        } else {
+            for report in &reports {
+                println!("{}", report);
+            }
+        }
+
+        std::process::exit(if printable { 0 } else { 1 });
+    }
+
+    //Volumes change the parts they overlap and aren't printed themselves
+    let (models, volumes): (Vec<InputModel>, Vec<InputModel>) = models
+        .into_iter()
+        .partition(|model| matches!(model.volume, VolumeType::Part));
+
+    display_state_update("Checking Build Volume", send_messages);
+
+    handle_err_or_return(check_model_bounds(&models, &settings), send_messages);
+
     display_state_update("Creating Towers", send_messages);
 
     let towers: Vec<TriangleTower> = handle_err_or_return(create_towers(&models), send_messages);
 
     display_state_update("Slicing", send_messages);
 
-    let objects = handle_err_or_return(slice(&towers, &settings), send_messages);
+    let layer_heights: Vec<Vec<(f64, f64, f64)>> = models
+        .par_iter()
+        .map(|model| get_layer_heights(model, &model.settings))
+        .collect();
+
+    let mut objects = handle_err_or_return(slice(&towers, &layer_heights, &models), send_messages);
+
+    if !volumes.is_empty() {
+        display_state_update("Slicing Volumes", send_messages);
+
+        let volume_towers = handle_err_or_return(create_towers(&volumes), send_messages);
+        handle_err_or_return(
+            apply_volumes(&mut objects, &layer_heights, &volumes, &volume_towers),
+            send_messages,
+        );
+    }
 
     display_state_update("Generating Moves", send_messages);
 
-    let mut moves = handle_err_or_return(
+    let (object_descriptions, mut moves) = handle_err_or_return(
         generate_moves(objects, &settings, send_messages),
         send_messages,
     );
@@ -121,7 +196,15 @@ fn main() {
 
     SlowDownLayerPass::pass(&mut moves, &settings);
 
+    handle_err_or_return(
+        check_command_bounds(&moves, &object_descriptions, &settings),
+        send_messages,
+    );
+
     if send_messages {
+        let message = Message::Objects(object_descriptions.clone());
+        bincode::serialize_into(BufWriter::new(std::io::stdout()), &message).unwrap();
+
         let message = Message::Commands(moves.clone());
         bincode::serialize_into(BufWriter::new(std::io::stdout()), &message).unwrap();
     }
@@ -159,6 +242,7 @@ fn main() {
         debug!("Converting {} Moves", moves.len());
         convert(
             &moves,
+            &object_descriptions,
             settings,
             &mut File::create(file_path).expect("File not Found"),
         )
@@ -166,14 +250,14 @@ fn main() {
     } else if send_messages {
         //Output as message
         let mut gcode: Vec<u8> = Vec::new();
-        convert(&moves, settings, &mut gcode).unwrap();
+        convert(&moves, &object_descriptions, settings, &mut gcode).unwrap();
         let message = Message::GCode(String::from_utf8(gcode).unwrap());
         bincode::serialize_into(BufWriter::new(std::io::stdout()), &message).unwrap();
     } else {
         //Output to stdout
         let stdout = std::io::stdout();
         debug!("Converting {} Moves", moves.len());
-        convert(&moves, settings, &mut stdout.lock()).unwrap();
+        convert(&moves, &object_descriptions, settings, &mut stdout.lock()).unwrap();
     };
 }
 
@@ -181,24 +265,30 @@ fn generate_moves(
     mut objects: Vec<Object>,
     settings: &Settings,
     send_messages: bool,
-) -> Result<Vec<Command>, SlicerErrors> {
+) -> Result<(Vec<ObjectDescription>, Vec<Command>), SlicerErrors> {
     //Creates Support Towers
     SupportTowerPass::pass(&mut objects, settings, send_messages);
 
-    //Adds a skirt
-    SkirtPass::pass(&mut objects, settings, send_messages);
+    //Copies and objects printed one at a time need the brim and skirt to be added after they are placed
+    let late_brim_and_skirt = settings.sequential_printing.is_some()
+        || objects.iter().any(|object| !object.copies.is_empty());
 
-    //Adds a brim
-    BrimPass::pass(&mut objects, settings, send_messages);
+    if !late_brim_and_skirt {
+        add_brim_and_skirt(&mut objects, settings, send_messages)?;
+    }
 
     let v: Result<Vec<()>, SlicerErrors> = objects
         .par_iter_mut()
         .map(|object| {
             let slices = &mut object.layers;
+            let settings = &object.settings;
 
             //Shrink layer
             ShrinkPass::pass(slices, settings, send_messages)?;
 
+            //Spiral the outer perimeter in vase mode
+            VaseModePass::pass(slices, settings, send_messages)?;
+
             //Handle Perimeters
             PerimeterPass::pass(slices, settings, send_messages)?;
 
@@ -214,6 +304,9 @@ fn generate_moves(
             //Handle Support
             SupportPass::pass(slices, settings, send_messages)?;
 
+            //Fill the regions of modifier volumes
+            ModifierPass::pass(slices, settings, send_messages)?;
+
             //Lightning Infill
             LightningFillPass::pass(slices, settings, send_messages)?;
 
@@ -227,7 +320,58 @@ fn generate_moves(
 
     v?;
 
-    Ok(convert_objects_into_moves(objects, settings))
+    //Copies reuse the moves of their object so they are added after the moves are generated
+    CopyPass::pass(&mut objects, settings, send_messages);
+
+    //Objects printed one at a time are ordered before the brim and skirt are added to the first object
+    if let Some(sequential_settings) = &settings.sequential_printing {
+        display_state_update("Ordering Objects For Sequential Printing", send_messages);
+        order_objects_for_sequential_printing(&mut objects, sequential_settings)?;
+    }
+
+    if late_brim_and_skirt {
+        add_brim_and_skirt(&mut objects, settings, send_messages)?;
+    }
+
+    //Commands refer to the objects by their index after the objects are ordered
+    let object_descriptions = objects.iter().map(ObjectDescription::from).collect();
+
+    Ok((
+        object_descriptions,
+        convert_objects_into_moves(objects, settings),
+    ))
+}
+
+///Add the brim and skirt to the first object and check that they are inside the build volume
+fn add_brim_and_skirt(
+    objects: &mut Vec<Object>,
+    settings: &Settings,
+    send_messages: bool,
+) -> Result<(), SlicerErrors> {
+    //The brim and skirt are added before the chains already on the layers
+    let existing_chains: Vec<usize> = objects
+        .iter()
+        .take(1)
+        .flat_map(|object| object.layers.iter().map(|slice| slice.fixed_chains.len()))
+        .collect();
+
+    //Adds a brim
+    BrimPass::pass(objects, settings, send_messages);
+
+    //Adds a skirt
+    SkirtPass::pass(objects, settings, send_messages);
+
+    check_skirt_and_brim_bounds(
+        objects
+            .iter()
+            .take(1)
+            .flat_map(|object| object.layers.iter())
+            .zip(existing_chains)
+            .flat_map(|(slice, existing)| {
+                slice.fixed_chains[..slice.fixed_chains.len() - existing].iter()
+            }),
+        settings,
+    )
 }
 
 fn handle_err_or_return<T>(res: Result<T, SlicerErrors>, send_message: bool) -> T {
@@ -9,6 +9,7 @@ pub fn unary_optimizer(cmds: &mut Vec<Command>) {
     cmds.retain(|cmd| match cmd {
         Command::MoveTo { .. } => true,
         Command::MoveAndExtrude { start, end, .. } => start != end,
+        Command::MoveAndExtrudeZ { start, end, .. } => start != end,
         Command::LayerChange { .. } => true,
         Command::ChangeObject { .. } => true,
         Command::SetState { new_state } => {
@@ -107,6 +108,11 @@ pub fn binary_optimizer(cmds: &mut Vec<Command>, settings: &Settings) {
                         start: _s_start,
                         end: s_end,
                         ..
+                    }
+                    | Command::MoveAndExtrudeZ {
+                        start: _s_start,
+                        end: s_end,
+                        ..
                     },
                 ) => {
                     current_pos = s_end;
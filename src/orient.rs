@@ -0,0 +1,224 @@
+use gladius_shared::settings::Settings;
+use gladius_shared::types::*;
+use log::debug;
+use nalgebra::{Point3, Rotation3, Unit, Vector3};
+use std::collections::HashMap;
+use std::f64::consts::PI;
+
+///Overhang angle used when support is disabled
+const DEFAULT_MAX_OVERHANG_ANGLE: f64 = 45.0;
+
+///Number of the largest faces to try placing on the bed
+const FACE_CANDIDATES: usize = 10;
+
+///How much bed contact reduces the score compared to overhangs
+const CONTACT_WEIGHT: f64 = 0.5;
+
+///How much height increases the score compared to overhangs
+const HEIGHT_WEIGHT: f64 = 0.1;
+
+///Triangles within this distance in mm of the bottom of the model are touching the bed
+const BED_CONTACT_DISTANCE: f64 = 0.01;
+
+struct Facet {
+    normal: Vector3<f64>,
+    area: f64,
+    points: [Point3<f64>; 3],
+}
+
+///Get the rotation that orients the models for the least overhang and most bed contact while keeping them short
+pub fn get_auto_orient_transform<'a>(
+    models: impl Iterator<Item = (&'a [Vertex], &'a [IndexedTriangle])>,
+    transform: &Transform,
+    settings: &Settings,
+) -> Transform {
+    let facets: Vec<Facet> = models
+        .flat_map(|(vertices, triangles)| {
+            triangles.iter().filter_map(move |tri| {
+                let points = tri
+                    .verts
+                    .map(|index| Point3::from(transform * vertices[index]));
+                let cross = (points[1] - points[0]).cross(&(points[2] - points[0]));
+                let area = cross.norm() / 2.0;
+
+                if area > 0.0 {
+                    Some(Facet {
+                        normal: cross.normalize(),
+                        area,
+                        points,
+                    })
+                } else {
+                    None
+                }
+            })
+        })
+        .collect();
+
+    let max_overhang_angle = settings
+        .support
+        .as_ref()
+        .map(|support| support.max_overhang_angle)
+        .unwrap_or(DEFAULT_MAX_OVERHANG_ANGLE);
+
+    let best = get_candidate_rotations(&facets)
+        .into_iter()
+        .map(|rotation| {
+            let score = score_rotation(&facets, &rotation, max_overhang_angle);
+            (rotation, score)
+        })
+        .fold(
+            None,
+            |best: Option<(Rotation3<f64>, f64)>, (rotation, score)| match best {
+                Some((_, best_score)) if best_score <= score => best,
+                _ => Some((rotation, score)),
+            },
+        )
+        .map(|(rotation, _)| rotation)
+        .unwrap_or_else(Rotation3::identity);
+
+    let (x, y, z) = best.euler_angles();
+    debug!(
+        "Auto orient rotation {:.1} {:.1} {:.1}",
+        x.to_degrees(),
+        y.to_degrees(),
+        z.to_degrees()
+    );
+
+    Transform::new_rotation_transform(x.to_degrees(), y.to_degrees(), z.to_degrees())
+}
+
+///Each side of the model down plus the rotations that place the largest faces on the bed
+fn get_candidate_rotations(facets: &[Facet]) -> Vec<Rotation3<f64>> {
+    let mut candidates = vec![
+        Rotation3::identity(),
+        Rotation3::from_euler_angles(PI / 2.0, 0.0, 0.0),
+        Rotation3::from_euler_angles(PI, 0.0, 0.0),
+        Rotation3::from_euler_angles(-PI / 2.0, 0.0, 0.0),
+        Rotation3::from_euler_angles(0.0, PI / 2.0, 0.0),
+        Rotation3::from_euler_angles(0.0, -PI / 2.0, 0.0),
+    ];
+
+    //Group facets with the same normal so flat faces made of many triangles are found
+    let mut faces: HashMap<(i64, i64, i64), (Vector3<f64>, f64)> = HashMap::new();
+    for facet in facets {
+        let key = (
+            (facet.normal.x * 100.0).round() as i64,
+            (facet.normal.y * 100.0).round() as i64,
+            (facet.normal.z * 100.0).round() as i64,
+        );
+        let face = faces.entry(key).or_insert((Vector3::zeros(), 0.0));
+        face.0 += facet.normal * facet.area;
+        face.1 += facet.area;
+    }
+
+    let mut faces: Vec<(Vector3<f64>, f64)> = faces.into_values().collect();
+    faces.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+
+    let down = -Vector3::z();
+    candidates.extend(faces.iter().take(FACE_CANDIDATES).map(|(normal, _)| {
+        //Normals pointing straight up have no unique rotation down so flip around x
+        Rotation3::rotation_between(normal, &down)
+            .unwrap_or_else(|| Rotation3::from_axis_angle(&Unit::new_normalize(Vector3::x()), PI))
+    }));
+
+    candidates
+}
+
+///Lower scores are better
+fn score_rotation(facets: &[Facet], rotation: &Rotation3<f64>, max_overhang_angle: f64) -> f64 {
+    //Facets steeper than the max overhang angle from vertical need support
+    let overhang_threshold = max_overhang_angle.to_radians().sin();
+
+    let (min_z, max_z) = facets
+        .iter()
+        .flat_map(|facet| facet.points.iter())
+        .map(|point| (rotation * point).z)
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), z| {
+            (min.min(z), max.max(z))
+        });
+
+    let mut overhang_area = 0.0;
+    let mut contact_area = 0.0;
+    let mut total_area = 0.0;
+    let mut max_size: f64 = 0.0;
+
+    for facet in facets {
+        let normal = rotation * facet.normal;
+        total_area += facet.area;
+
+        if -normal.z > overhang_threshold {
+            let on_bed = facet
+                .points
+                .iter()
+                .all(|point| (rotation * point).z - min_z < BED_CONTACT_DISTANCE);
+
+            if on_bed {
+                contact_area += facet.area;
+            } else {
+                overhang_area += facet.area;
+            }
+        }
+
+        for point in &facet.points {
+            max_size = max_size.max(point.coords.norm());
+        }
+    }
+
+    if total_area == 0.0 {
+        return 0.0;
+    }
+
+    //Heights are compared to a size that doesn't change with rotation
+    let height = (max_z - min_z) / max_size.max(f64::EPSILON);
+
+    (overhang_area - CONTACT_WEIGHT * contact_area) / total_area + HEIGHT_WEIGHT * height
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn standing_plate_is_laid_flat() {
+        //A 20 x 1 x 20 plate standing on its edge
+        let vertices: Vec<Vertex> = (0..8)
+            .map(|i| Vertex {
+                x: if i & 1 == 0 { 0.0 } else { 20.0 },
+                y: if i & 2 == 0 { 0.0 } else { 1.0 },
+                z: if i & 4 == 0 { 0.0 } else { 20.0 },
+            })
+            .collect();
+        let triangles: Vec<IndexedTriangle> = [
+            [0, 2, 1],
+            [1, 2, 3],
+            [4, 5, 6],
+            [5, 7, 6],
+            [0, 1, 4],
+            [1, 5, 4],
+            [2, 6, 3],
+            [3, 6, 7],
+            [0, 4, 2],
+            [2, 4, 6],
+            [1, 3, 5],
+            [3, 7, 5],
+        ]
+        .iter()
+        .map(|&verts| IndexedTriangle { verts })
+        .collect();
+
+        let transform = get_auto_orient_transform(
+            std::iter::once((vertices.as_slice(), triangles.as_slice())),
+            &Transform::new_translation_transform(0.0, 0.0, 0.0),
+            &Settings::default(),
+        );
+
+        let (min_z, max_z) = vertices
+            .iter()
+            .map(|v| (&transform * *v).z)
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), z| {
+                (min.min(z), max.max(z))
+            });
+
+        assert!((max_z - min_z - 1.0).abs() < 0.000_001);
+    }
+}
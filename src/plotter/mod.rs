@@ -8,13 +8,13 @@ pub(crate) mod support;
 pub use crate::plotter::infill::*;
 use crate::plotter::perimeter::*;
 use crate::plotter::polygon_operations::PolygonOperations;
-use crate::{Object, Settings, StateChange};
+use crate::{Object, Settings, SlicerErrors, StateChange};
 use geo::coordinate_position::CoordPos;
 use geo::coordinate_position::CoordinatePosition;
 use geo::prelude::*;
 use geo::*;
 use gladius_shared::settings::SkirtSettings;
-use gladius_shared::types::{Command, Move, MoveChain, MoveType, Slice};
+use gladius_shared::types::{Command, Move, MoveChain, MoveType, PartialInfillTypes, Slice};
 use itertools::Itertools;
 use log::info;
 use ordered_float::OrderedFloat;
@@ -23,11 +23,16 @@ pub trait Plotter {
     fn slice_perimeters_into_chains(&mut self, number_of_perimeters: usize);
     fn shrink_layer(&mut self);
     fn fill_remaining_area(&mut self, solid: bool, layer_count: usize);
+    fn fill_modifier_regions(&mut self, layer_count: usize);
     fn fill_solid_subtracted_area(&mut self, other: &MultiPolygon<f64>, layer_count: usize);
     fn fill_solid_bridge_area(&mut self, layer_below: &MultiPolygon<f64>);
     fn fill_solid_top_layer(&mut self, layer_above: &MultiPolygon<f64>, layer_count: usize);
     fn generate_skirt(&mut self, convex_polygon: &Polygon<f64>, skirt_settings: &SkirtSettings);
     fn generate_brim(&mut self, entire_first_layer: MultiPolygon<f64>, brim_width: f64);
+    fn spiral_outer_perimeter(
+        &mut self,
+        previous_end: Option<Coordinate<f64>>,
+    ) -> Result<(), SlicerErrors>;
     fn order_chains(&mut self);
     fn slice_into_commands(&mut self, commands: &mut Vec<Command>, layer_thickness: f64);
 }
@@ -95,6 +100,27 @@ impl Plotter for Slice {
         self.remaining_area = MultiPolygon(vec![])
     }
 
+    fn fill_modifier_regions(&mut self, layer_count: usize) {
+        for (region, layer_settings) in &self.modifier_regions {
+            //Lightning infill is generated for the whole object so those regions use the object's infill
+            if layer_settings.partial_infill_type == PartialInfillTypes::Lightning {
+                continue;
+            }
+
+            for poly in &self.remaining_area.intersection_with(region) {
+                self.chains.extend(partial_infill_polygon(
+                    poly,
+                    layer_settings,
+                    layer_settings.infill_percentage,
+                    layer_count,
+                    self.get_height(),
+                ));
+            }
+
+            self.remaining_area = self.remaining_area.difference_with(region);
+        }
+    }
+
     fn fill_solid_subtracted_area(&mut self, other: &MultiPolygon<f64>, layer_count: usize) {
         //For each area not in this slice that is in the other polygon, fill solid
 
@@ -218,6 +244,63 @@ impl Plotter for Slice {
         );
     }
 
+    fn spiral_outer_perimeter(
+        &mut self,
+        previous_end: Option<Coordinate<f64>>,
+    ) -> Result<(), SlicerErrors> {
+        let outlines = self
+            .remaining_area
+            .offset_from(-self.layer_settings.layer_width / 2.0);
+
+        //Only one island can be printed as a single spiral, an island whose outline splits is more than one
+        let islands = self.remaining_area.0.len().max(outlines.0.len());
+        if islands > 1 {
+            return Err(SlicerErrors::VaseModeMultipleIslands {
+                height: self.top_height,
+                islands,
+            });
+        }
+
+        let outer_polygon = outlines.0.into_iter().next();
+
+        if let Some(polygon) = outer_polygon {
+            let polygon = polygon.simplify(&0.01);
+            let mut points = polygon.exterior().0.clone();
+            points.pop();
+
+            //Start where the last layer ended to keep the spiral continuous
+            let start_index = previous_end
+                .and_then(|end| {
+                    points
+                        .iter()
+                        .position_min_by_key(|point| OrderedFloat(point.euclidean_distance(&end)))
+                })
+                .unwrap_or(0);
+            points.rotate_left(start_index);
+
+            if let Some(&start_point) = points.first() {
+                let layer_width = self.layer_settings.layer_width;
+                self.spiral_chain = Some(MoveChain {
+                    start_point,
+                    moves: points
+                        .into_iter()
+                        .skip(1)
+                        .chain(std::iter::once(start_point))
+                        .map(|end| Move {
+                            end,
+                            move_type: MoveType::OuterPerimeter,
+                            width: layer_width,
+                        })
+                        .collect(),
+                });
+            }
+        }
+
+        self.remaining_area = MultiPolygon(vec![]);
+
+        Ok(())
+    }
+
     fn order_chains(&mut self) {
         //Order Chains for fastest print
         let ordered_chains = if !self.chains.is_empty() {
@@ -273,6 +356,24 @@ impl Plotter for Slice {
                 .create_commands(&self.layer_settings, layer_thickness),
             );
         }
+
+        if let Some(mut spiral_chain) = self.spiral_chain.take() {
+            spiral_chain.moves.insert(
+                0,
+                Move {
+                    end: spiral_chain.start_point,
+                    move_type: MoveType::Travel,
+                    width: 0.0,
+                },
+            );
+
+            commands.append(&mut spiral_chain.create_spiral_commands(
+                &self.layer_settings,
+                layer_thickness,
+                self.bottom_height,
+                self.top_height,
+            ));
+        }
     }
 }
 
@@ -334,32 +435,41 @@ fn get_optimal_bridge_angle(fill_area: &Polygon<f64>, unsupported_area: &MultiPo
 
 pub fn convert_objects_into_moves(objects: Vec<Object>, settings: &Settings) -> Vec<Command> {
     info!("Convert into Commnds");
-    let mut layer_moves: Vec<(f64, Vec<Command>)> = objects
+    let object_layer_moves: Vec<Vec<(f64, Vec<Command>)>> = objects
         .into_iter()
         .enumerate()
         .map(|(object_num, object)| {
             let mut last_layer = 0.0;
+            let object_settings = object.settings;
 
             object
                 .layers
                 .into_iter()
                 .enumerate()
                 .map(|(layer_num, mut slice)| {
-                    let layer_settings = settings.get_layer_settings(layer_num, slice.top_height);
+                    let layer_settings =
+                        object_settings.get_layer_settings(layer_num, slice.top_height);
                     let mut moves = vec![];
                     moves.push(Command::ChangeObject { object: object_num });
+                    //Spirals rise from the bottom of the layer
                     moves.push(Command::LayerChange {
-                        z: slice.top_height,
+                        z: if slice.spiral_chain.is_some() {
+                            slice.bottom_height
+                        } else {
+                            slice.top_height
+                        },
                     });
                     moves.push(Command::SetState {
                         new_state: StateChange {
                             extruder_temp: Some(layer_settings.extruder_temp),
                             bed_temp: Some(layer_settings.bed_temp),
-                            fan_speed: Some(if layer_num < settings.fan.disable_fan_for_layers {
-                                0.0
-                            } else {
-                                settings.fan.fan_speed
-                            }),
+                            fan_speed: Some(
+                                if layer_num < object_settings.fan.disable_fan_for_layers {
+                                    0.0
+                                } else {
+                                    object_settings.fan.fan_speed
+                                },
+                            ),
                             movement_speed: None,
                             acceleration: None,
                             retract: None,
@@ -372,6 +482,14 @@ pub fn convert_objects_into_moves(objects: Vec<Object>, settings: &Settings) ->
                 })
                 .collect::<Vec<(f64, Vec<Command>)>>()
         })
+        .collect();
+
+    if settings.sequential_printing.is_some() {
+        return sequential_moves(object_layer_moves, settings);
+    }
+
+    let mut layer_moves: Vec<(f64, Vec<Command>)> = object_layer_moves
+        .into_iter()
         .flat_map(|a| a.into_iter())
         .collect();
 
@@ -383,3 +501,82 @@ pub fn convert_objects_into_moves(objects: Vec<Object>, settings: &Settings) ->
         .flat_map(|(_, layer_moves)| layer_moves)
         .collect()
 }
+
+///Print each object to completion, travelling above the printed objects to the start of the next one
+fn sequential_moves(
+    object_layer_moves: Vec<Vec<(f64, Vec<Command>)>>,
+    settings: &Settings,
+) -> Vec<Command> {
+    let mut printed_height: f64 = 0.0;
+    let mut moves = vec![];
+
+    for layer_moves in object_layer_moves {
+        let start_point = layer_moves
+            .iter()
+            .flat_map(|(_, layer_moves)| layer_moves.iter())
+            .find_map(|cmd| match cmd {
+                Command::MoveTo { end } => Some(*end),
+                _ => None,
+            });
+
+        if let (false, Some(end)) = (moves.is_empty(), start_point) {
+            moves.push(Command::SetState {
+                new_state: StateChange {
+                    extruder_temp: None,
+                    bed_temp: None,
+                    fan_speed: None,
+                    movement_speed: Some(settings.speed.travel),
+                    acceleration: Some(settings.acceleration.travel),
+                    retract: Some(true),
+                },
+            });
+            moves.push(Command::LayerChange {
+                z: printed_height + settings.retract_lift_z,
+            });
+            moves.push(Command::MoveTo { end });
+        }
+
+        for (height, mut layer_moves) in layer_moves {
+            printed_height = printed_height.max(height);
+            moves.append(&mut layer_moves);
+        }
+    }
+
+    moves
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    ///A layer of 10mm squares starting at each x offset
+    fn squares(offsets: &[f64]) -> Slice {
+        let lines = offsets
+            .iter()
+            .map(|&x| {
+                LineString::from(vec![
+                    (x, 0.0),
+                    (x + 10.0, 0.0),
+                    (x + 10.0, 10.0),
+                    (x, 10.0),
+                    (x, 0.0),
+                ])
+            })
+            .collect();
+
+        Slice::from_multiple_point_loop(lines, 1.0, 1.2, 5, &Settings::default()).unwrap()
+    }
+
+    #[test]
+    fn spiral_layers_have_one_island() {
+        let mut slice = squares(&[0.0]);
+        slice.spiral_outer_perimeter(None).unwrap();
+        assert!(slice.spiral_chain.is_some());
+
+        let mut slice = squares(&[0.0, 20.0]);
+        assert!(matches!(
+            slice.spiral_outer_perimeter(None),
+            Err(SlicerErrors::VaseModeMultipleIslands { islands: 2, .. })
+        ));
+    }
+}
@@ -1,11 +1,13 @@
 use crate::plotter::support_linear_fill_polygon;
 use crate::{MoveType, PolygonOperations, Slice};
 use geo::MultiPolygon;
-use gladius_shared::settings::SupportSettings;
+use gladius_shared::settings::{SupportInterfaceSettings, SupportSettings};
+use gladius_shared::types::SupportInterfacePattern;
+use rayon::prelude::*;
 
 pub trait Supporter {
     fn add_support_polygons(&mut self, slice_above: &Slice, support_settings: &SupportSettings);
-    fn fill_support_polygons(&mut self, support_settings: &SupportSettings);
+    fn fill_support_polygons(&mut self, support_settings: &SupportSettings, layer_count: usize);
     fn get_support_polygon(&self) -> MultiPolygon<f64>;
 }
 
@@ -16,10 +18,19 @@ impl Supporter for Slice {
             distance_between_layers * support_settings.max_overhang_angle.to_radians().tan();
 
         let current_polygon_support_area = self.main_polygon.offset_from(max_overhang_distance);
-        let unsupported_above_area = slice_above
+        let mut unsupported_above_area = slice_above
             .main_polygon
             .difference_with(&current_polygon_support_area);
 
+        //Enforced overhangs are supported no matter the angle
+        if !self.support_enforcers.0.is_empty() {
+            let enforced_area = slice_above
+                .main_polygon
+                .intersection_with(&self.support_enforcers)
+                .difference_with(&self.main_polygon);
+            unsupported_above_area = unsupported_above_area.union_with(&enforced_area);
+        }
+
         if !unsupported_above_area.0.is_empty() {
             self.support_interface = Some(unsupported_above_area);
         }
@@ -37,21 +48,51 @@ impl Supporter for Slice {
         } else if let Some(above_support_tower) = &slice_above.support_tower {
             self.support_tower = Some(above_support_tower.clone());
         }
+
+        //Blocked support is removed from this layer so it is not carried down to the layers below either
+        if !self.support_blockers.0.is_empty() {
+            let blockers = &self.support_blockers;
+            self.support_interface = self
+                .support_interface
+                .as_ref()
+                .map(|interface| interface.difference_with(blockers))
+                .filter(|interface| !interface.0.is_empty());
+            self.support_tower = self
+                .support_tower
+                .as_ref()
+                .map(|tower| tower.difference_with(blockers));
+        }
     }
 
-    fn fill_support_polygons(&mut self, support_settings: &SupportSettings) {
+    fn fill_support_polygons(&mut self, support_settings: &SupportSettings, layer_count: usize) {
         let layer_settings = &self.layer_settings;
-        /* if let Some(tower_polygon) = &self.support_interface{
 
-            self.fixed_chains.extend(
-                tower_polygon
-                    .iter()
-                    .map(|poly| {
-                        linear_fill_polygon(poly,layer_settings,MoveType::Support,0.0).into_iter()
+        if let (Some(interface_polygon), Some(interface_settings)) =
+            (&self.support_interface, &support_settings.interface)
+        {
+            if interface_settings.density > 0.0 {
+                let angles = match interface_settings.pattern {
+                    SupportInterfacePattern::Linear => vec![90.0 * (layer_count % 2) as f64],
+                    SupportInterfacePattern::Rectilinear => vec![0.0, 90.0],
+                };
+                let spacing = layer_settings.layer_width * angles.len() as f64
+                    / interface_settings.density.min(1.0);
+
+                self.fixed_chains.extend(angles.iter().flat_map(|angle| {
+                    interface_polygon.iter().flat_map(move |poly| {
+                        support_linear_fill_polygon(
+                            poly,
+                            layer_settings,
+                            MoveType::SupportInterface,
+                            spacing,
+                            *angle,
+                            0.0,
+                        )
+                        .into_iter()
                     })
-                    .flatten()
-            );
-        }*/
+                }));
+            }
+        }
 
         if let Some(tower_polygon) = &self.support_tower {
             self.fixed_chains
@@ -78,3 +119,185 @@ impl Supporter for Slice {
         }
     }
 }
+
+///Move the support closest to the model into the interface and leave the rest as towers.
+/// Support is removed within the gaps to the model, the interface is the support under the next
+/// top layers of the model above the gap and over the next bottom layers of the model below the gap.
+pub fn split_support_interface(
+    slices: &mut [Slice],
+    interface_settings: &SupportInterfaceSettings,
+) {
+    let split: Vec<Option<(MultiPolygon<f64>, MultiPolygon<f64>)>> = (0..slices.len())
+        .into_par_iter()
+        .map(|q| {
+            let slice = &slices[q];
+            let support = slice.get_support_polygon();
+            if support.0.is_empty() {
+                return None;
+            }
+            let support =
+                support.difference_with(&slice.main_polygon.offset_from(interface_settings.xy_gap));
+
+            let above_gap = slices[q + 1..]
+                .iter()
+                .take_while(|above| {
+                    above.bottom_height < slice.top_height + interface_settings.z_gap - 0.000_01
+                })
+                .count();
+            let below_gap = slices[..q]
+                .iter()
+                .rev()
+                .take_while(|below| {
+                    below.top_height > slice.bottom_height - interface_settings.z_gap + 0.000_01
+                })
+                .count();
+
+            let support = support
+                .difference_with(&union_of_main_polygons(
+                    slices[q + 1..].iter().take(above_gap),
+                ))
+                .difference_with(&union_of_main_polygons(
+                    slices[..q].iter().rev().take(below_gap),
+                ));
+
+            let model_near = union_of_main_polygons(
+                slices[q + 1..]
+                    .iter()
+                    .skip(above_gap)
+                    .take(interface_settings.top_layers)
+                    .chain(
+                        slices[..q]
+                            .iter()
+                            .rev()
+                            .skip(below_gap)
+                            .take(interface_settings.bottom_layers),
+                    ),
+            );
+
+            let interface = support.intersection_with(&model_near);
+            let tower = support.difference_with(&interface);
+
+            Some((interface, tower))
+        })
+        .collect();
+
+    for (slice, split) in slices.iter_mut().zip(split) {
+        if let Some((interface, tower)) = split {
+            slice.support_interface = Some(interface).filter(|poly| !poly.0.is_empty());
+            slice.support_tower = Some(tower).filter(|poly| !poly.0.is_empty());
+        }
+    }
+}
+
+fn union_of_main_polygons<'a>(layers: impl Iterator<Item = &'a Slice>) -> MultiPolygon<f64> {
+    layers.fold(MultiPolygon(vec![]), |a, b| a.union_with(&b.main_polygon))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo::prelude::*;
+    use geo::{polygon, MultiPolygon};
+    use gladius_shared::settings::Settings;
+
+    fn square_slice(size: f64, layer: usize) -> Slice {
+        let square = [(0.0, 0.0), (size, 0.0), (size, size), (0.0, size)];
+
+        Slice::from_single_point_loop(
+            square.iter().copied(),
+            layer as f64 * 0.2,
+            (layer + 1) as f64 * 0.2,
+            layer,
+            &Settings::default(),
+        )
+    }
+
+    fn region(min: f64, max: f64) -> MultiPolygon<f64> {
+        MultiPolygon(vec![polygon![
+            (x: min, y: min),
+            (x: max, y: min),
+            (x: max, y: max),
+            (x: min, y: max),
+        ]])
+    }
+
+    #[test]
+    fn blockers_remove_support_and_enforcers_add_it() {
+        let support_settings = SupportSettings {
+            max_overhang_angle: 45.0,
+            support_spacing: 2.0,
+            interface: None,
+        };
+
+        //A large overhang is normally supported but not inside a blocker
+        let mut below = square_slice(5.0, 0);
+        let above = square_slice(20.0, 1);
+        below.support_blockers = region(-1.0, 21.0);
+        below.add_support_polygons(&above, &support_settings);
+        assert!(below.support_interface.is_none());
+
+        //A small overhang is normally unsupported but is inside an enforcer
+        let mut below = square_slice(10.0, 0);
+        let above = square_slice(10.1, 1);
+        below.add_support_polygons(&above, &support_settings);
+        assert!(below.support_interface.is_none());
+
+        below.support_enforcers = region(-1.0, 21.0);
+        below.add_support_polygons(&above, &support_settings);
+        let interface = below.support_interface.expect("Enforced support");
+        assert!((interface.unsigned_area() - (10.1 * 10.1 - 100.0)).abs() < 0.01);
+    }
+
+    #[test]
+    fn support_next_to_the_model_is_split_into_interface() {
+        let support_settings = SupportSettings {
+            max_overhang_angle: 45.0,
+            support_spacing: 2.0,
+            interface: Some(SupportInterfaceSettings {
+                top_layers: 2,
+                bottom_layers: 0,
+                density: 1.0,
+                pattern: SupportInterfacePattern::Linear,
+                z_gap: 0.2,
+                xy_gap: 0.5,
+            }),
+        };
+
+        //A thin pillar holding up a large slab
+        let mut layers: Vec<Slice> = (0..12)
+            .map(|layer| square_slice(if layer < 10 { 5.0 } else { 20.0 }, layer))
+            .collect();
+        for q in (1..layers.len()).rev() {
+            if let [layer, above] = &mut layers[q - 1..=q] {
+                layer.add_support_polygons(above, &support_settings);
+            }
+        }
+        split_support_interface(&mut layers, support_settings.interface.as_ref().unwrap());
+
+        //The layer under the slab is the gap
+        assert!(layers[9].get_support_polygon().0.is_empty());
+
+        for (layer_num, slice) in layers.iter_mut().enumerate().take(9) {
+            let interface_area = slice
+                .support_interface
+                .as_ref()
+                .map(|interface| interface.unsigned_area())
+                .unwrap_or(0.0);
+            if layer_num >= 7 {
+                assert!(interface_area > 300.0);
+            } else {
+                assert_eq!(interface_area, 0.0);
+                assert!(slice.support_tower.is_some());
+            }
+
+            slice.fill_support_polygons(&support_settings, layer_num);
+            let has_interface_moves = slice.fixed_chains.iter().any(|chain| {
+                chain
+                    .moves
+                    .iter()
+                    .any(|m| m.move_type == MoveType::SupportInterface)
+            });
+            assert_eq!(has_interface_moves, layer_num >= 7);
+        }
+    }
+}
@@ -0,0 +1,485 @@
+use crate::input::InputModel;
+use gladius_shared::loader::rotate_lowest_vertex_first;
+use gladius_shared::settings::RepairSettings;
+use gladius_shared::types::*;
+use nalgebra::Point3;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::{Display, Formatter};
+
+///Summary of the changes made to a model by the repair pass
+#[derive(Default, Debug, PartialEq)]
+pub struct RepairReport {
+    ///Number of vertices merged into a nearby vertex
+    pub merged_vertices: usize,
+
+    ///Number of triangles removed because they had no area
+    pub removed_degenerate_triangles: usize,
+
+    ///Number of triangles removed because they were the same as another triangle
+    pub removed_duplicate_triangles: usize,
+
+    ///Number of triangles that had their orientation flipped
+    pub flipped_triangles: usize,
+
+    ///Number of holes closed
+    pub closed_holes: usize,
+}
+
+impl RepairReport {
+    ///Returns true if the repair changed the model
+    pub fn has_changes(&self) -> bool {
+        *self != RepairReport::default()
+    }
+}
+
+impl Display for RepairReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let changes: Vec<String> = [
+            (self.merged_vertices, "merged vertices"),
+            (
+                self.removed_degenerate_triangles,
+                "degenerate triangles removed",
+            ),
+            (
+                self.removed_duplicate_triangles,
+                "duplicate triangles removed",
+            ),
+            (self.flipped_triangles, "triangles flipped"),
+            (self.closed_holes, "holes closed"),
+        ]
+        .iter()
+        .filter(|(count, _)| *count != 0)
+        .map(|(count, description)| format!("{} {}", count, description))
+        .collect();
+
+        if changes.is_empty() {
+            write!(f, "no changes")
+        } else {
+            write!(f, "{}", changes.join(", "))
+        }
+    }
+}
+
+///Repair common mesh problems that would otherwise fail tower creation
+pub fn repair_model(model: &mut InputModel, settings: &RepairSettings) -> RepairReport {
+    let mut report = RepairReport::default();
+
+    let (vertices, vertex_map) = merge_vertices(&model.vertices, settings.merge_distance);
+    report.merged_vertices = model.vertices.len() - vertices.len();
+
+    let mut triangles: Vec<[usize; 3]> = model
+        .triangles
+        .iter()
+        .map(|tri| tri.verts.map(|index| vertex_map[index]))
+        .collect();
+
+    let count = triangles.len();
+    triangles.retain(|tri| !is_degenerate(tri, &vertices, settings.merge_distance));
+    report.removed_degenerate_triangles = count - triangles.len();
+
+    let count = triangles.len();
+    let mut seen = HashSet::new();
+    triangles.retain(|tri| {
+        let mut key = *tri;
+        key.sort_unstable();
+        seen.insert(key)
+    });
+    report.removed_duplicate_triangles = count - triangles.len();
+
+    //Track which of the original triangles end up flipped
+    let mut flipped = vec![false; triangles.len()];
+
+    orient_consistently(&mut triangles, &mut flipped);
+
+    report.closed_holes = close_holes(&mut triangles, settings.max_hole_edges);
+
+    orient_outwards(&mut triangles, &mut flipped, &vertices);
+
+    report.flipped_triangles = flipped.iter().filter(|flip| **flip).count();
+
+    model.triangles = triangles
+        .into_iter()
+        .map(|verts| rotate_lowest_vertex_first(IndexedTriangle { verts }, &vertices))
+        .collect();
+    model.vertices = vertices;
+
+    report
+}
+
+///Merge vertices closer than the merge distance, returns the new vertices and the new index of every old vertex
+fn merge_vertices(vertices: &[Vertex], merge_distance: f64) -> (Vec<Vertex>, Vec<usize>) {
+    let cell_size = merge_distance;
+    let cell = |v: &Vertex| {
+        (
+            (v.x / cell_size).floor() as i64,
+            (v.y / cell_size).floor() as i64,
+            (v.z / cell_size).floor() as i64,
+        )
+    };
+
+    let mut grid: HashMap<(i64, i64, i64), Vec<usize>> = HashMap::new();
+    let mut merged = vec![];
+
+    let vertex_map = vertices
+        .iter()
+        .map(|v| {
+            let (cx, cy, cz) = cell(v);
+
+            //A vertex within the merge distance must be in a neighbouring cell
+            let existing = (-1..=1)
+                .flat_map(|dx| (-1..=1).flat_map(move |dy| (-1..=1).map(move |dz| (dx, dy, dz))))
+                .filter_map(|(dx, dy, dz)| grid.get(&(cx + dx, cy + dy, cz + dz)))
+                .flatten()
+                .find(|index| distance(&merged[**index], v) <= merge_distance)
+                .copied();
+
+            existing.unwrap_or_else(|| {
+                merged.push(*v);
+                grid.entry((cx, cy, cz)).or_default().push(merged.len() - 1);
+                merged.len() - 1
+            })
+        })
+        .collect();
+
+    (merged, vertex_map)
+}
+
+fn distance(a: &Vertex, b: &Vertex) -> f64 {
+    ((a.x - b.x).powi(2) + (a.y - b.y).powi(2) + (a.z - b.z).powi(2)).sqrt()
+}
+
+///Triangles with twice their area below the square of the merge distance are too small to have a direction
+fn is_degenerate(tri: &[usize; 3], vertices: &[Vertex], merge_distance: f64) -> bool {
+    if tri[0] == tri[1] || tri[1] == tri[2] || tri[2] == tri[0] {
+        return true;
+    }
+
+    let a = Point3::from(vertices[tri[0]]);
+    let b = Point3::from(vertices[tri[1]]);
+    let c = Point3::from(vertices[tri[2]]);
+
+    (b - a).cross(&(c - a)).norm() <= merge_distance * merge_distance
+}
+
+fn flip(tri: &mut [usize; 3]) {
+    tri.swap(1, 2);
+}
+
+fn edges(tri: &[usize; 3]) -> [(usize, usize); 3] {
+    [(tri[0], tri[1]), (tri[1], tri[2]), (tri[2], tri[0])]
+}
+
+///Map from each undirected edge to the triangles that use it
+pub fn edge_triangles(triangles: &[[usize; 3]]) -> HashMap<(usize, usize), Vec<usize>> {
+    let mut map: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+
+    for (index, tri) in triangles.iter().enumerate() {
+        for (a, b) in edges(tri) {
+            map.entry((a.min(b), a.max(b))).or_default().push(index);
+        }
+    }
+
+    map
+}
+
+///Flip triangles so neighbouring triangles traverse their shared edge in opposite directions
+fn orient_consistently(triangles: &mut [[usize; 3]], flipped: &mut [bool]) {
+    let edge_map = edge_triangles(triangles);
+    let mut visited = vec![false; triangles.len()];
+
+    for start in 0..triangles.len() {
+        if visited[start] {
+            continue;
+        }
+
+        visited[start] = true;
+        let mut queue = VecDeque::from(vec![start]);
+
+        while let Some(current) = queue.pop_front() {
+            for (a, b) in edges(&triangles[current]) {
+                let neighbours = &edge_map[&(a.min(b), a.max(b))];
+
+                //Non manifold edges can't be oriented consistently
+                if neighbours.len() != 2 {
+                    continue;
+                }
+
+                for &neighbour in neighbours {
+                    if visited[neighbour] {
+                        continue;
+                    }
+
+                    //The neighbour must use the edge as b to a
+                    if edges(&triangles[neighbour]).contains(&(a, b)) {
+                        flip(&mut triangles[neighbour]);
+                        flipped[neighbour] = !flipped[neighbour];
+                    }
+
+                    visited[neighbour] = true;
+                    queue.push_back(neighbour);
+                }
+            }
+        }
+    }
+}
+
+///Close holes with at most max_edges edges, returns the number of holes closed
+fn close_holes(triangles: &mut Vec<[usize; 3]>, max_edges: usize) -> usize {
+    let directed_edges: HashSet<(usize, usize)> = triangles.iter().flat_map(edges).collect();
+
+    //Holes are walked in the opposite direction to the triangles around them
+    let mut hole_edges: HashMap<usize, Vec<usize>> = HashMap::new();
+    for &(a, b) in &directed_edges {
+        if !directed_edges.contains(&(b, a)) {
+            hole_edges.entry(b).or_default().push(a);
+        }
+    }
+
+    let mut closed = 0;
+    let mut used = HashSet::new();
+
+    let mut starts: Vec<usize> = hole_edges.keys().copied().collect();
+    starts.sort_unstable();
+
+    for start in starts {
+        if used.contains(&start) {
+            continue;
+        }
+
+        let mut hole = vec![start];
+        let mut current = start;
+
+        let complete = loop {
+            //Vertices on more than one hole edge are ambiguous so are left open
+            let next = match hole_edges.get(&current).map(|next| next.as_slice()) {
+                Some([next]) => *next,
+                _ => break false,
+            };
+
+            if next == start {
+                break true;
+            }
+
+            if hole.len() >= max_edges || hole.contains(&next) {
+                break false;
+            }
+
+            hole.push(next);
+            current = next;
+        };
+
+        used.extend(hole.iter().copied());
+
+        if complete && hole.len() >= 3 {
+            //Fan triangulation of the hole
+            for (second, third) in hole[1..].iter().zip(hole[2..].iter()) {
+                triangles.push([hole[0], *second, *third]);
+            }
+            closed += 1;
+        }
+    }
+
+    closed
+}
+
+///Group the triangles into connected parts
+pub fn connected_parts(
+    triangles: &[[usize; 3]],
+    edge_map: &HashMap<(usize, usize), Vec<usize>>,
+) -> Vec<Vec<usize>> {
+    let mut in_part = vec![false; triangles.len()];
+    let mut parts: Vec<Vec<usize>> = vec![];
+
+    for start in 0..triangles.len() {
+        if in_part[start] {
+            continue;
+        }
+
+        let mut members = vec![start];
+        in_part[start] = true;
+        let mut index = 0;
+
+        while index < members.len() {
+            for (a, b) in edges(&triangles[members[index]]) {
+                for &neighbour in &edge_map[&(a.min(b), a.max(b))] {
+                    if !in_part[neighbour] {
+                        in_part[neighbour] = true;
+                        members.push(neighbour);
+                    }
+                }
+            }
+            index += 1;
+        }
+
+        parts.push(members);
+    }
+
+    parts
+}
+
+///The signed volume enclosed by the triangles, negative if the normals face inward
+pub fn signed_volume(triangles: &[[usize; 3]], members: &[usize], vertices: &[Vertex]) -> f64 {
+    members
+        .iter()
+        .map(|index| {
+            let tri = &triangles[*index];
+            let a = Point3::from(vertices[tri[0]]).coords;
+            let b = Point3::from(vertices[tri[1]]).coords;
+            let c = Point3::from(vertices[tri[2]]).coords;
+            a.dot(&b.cross(&c)) / 6.0
+        })
+        .sum()
+}
+
+///Flip any connected part of the mesh that has a negative volume so its normals face outward
+fn orient_outwards(triangles: &mut [[usize; 3]], flipped: &mut [bool], vertices: &[Vertex]) {
+    let edge_map = edge_triangles(triangles);
+
+    for members in connected_parts(triangles, &edge_map) {
+        if signed_volume(triangles, &members, vertices) < 0.0 {
+            for index in members {
+                flip(&mut triangles[index]);
+
+                //Triangles added to close holes are not tracked
+                if let Some(flip) = flipped.get_mut(index) {
+                    *flip = !*flip;
+                }
+            }
+        }
+    }
+}
+
+///Count the triangles that would be flipped to make the normals consistent and face outward
+pub fn count_inverted_triangles(triangles: &[[usize; 3]], vertices: &[Vertex]) -> usize {
+    let mut triangles = triangles.to_vec();
+    let mut flipped = vec![false; triangles.len()];
+
+    orient_consistently(&mut triangles, &mut flipped);
+    orient_outwards(&mut triangles, &mut flipped, vertices);
+
+    flipped.iter().filter(|flip| **flip).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gladius_shared::error::SlicerErrors;
+    use gladius_shared::settings::{PartialSettings, Settings};
+
+    fn tetrahedron() -> InputModel {
+        InputModel {
+            name: "tetrahedron".to_string(),
+            metadata: vec![],
+            vertices: vec![
+                Vertex {
+                    x: 0.0,
+                    y: 0.0,
+                    z: 0.0,
+                },
+                Vertex {
+                    x: 1.0,
+                    y: 0.0,
+                    z: 0.0,
+                },
+                Vertex {
+                    x: 0.0,
+                    y: 1.0,
+                    z: 0.0,
+                },
+                Vertex {
+                    x: 0.0,
+                    y: 0.0,
+                    z: 1.0,
+                },
+            ],
+            triangles: [[0, 2, 1], [0, 1, 3], [1, 2, 3], [0, 3, 2]]
+                .iter()
+                .map(|verts| IndexedTriangle { verts: *verts })
+                .collect(),
+            copies: vec![],
+            settings: Settings::default(),
+            volume: VolumeType::Part,
+        }
+    }
+
+    const SETTINGS: RepairSettings = RepairSettings {
+        merge_distance: 0.001,
+        max_hole_edges: 10,
+    };
+
+    #[test]
+    fn closed_mesh_is_unchanged() {
+        let mut model = tetrahedron();
+        let report = repair_model(&mut model, &SETTINGS);
+
+        assert!(!report.has_changes());
+        assert_eq!(model.triangles.len(), 4);
+    }
+
+    #[test]
+    fn broken_mesh_is_repaired() {
+        let mut model = tetrahedron();
+
+        //Split a vertex, flip a triangle, remove a triangle and add a duplicate and a degenerate triangle
+        model.vertices.push(Vertex {
+            x: 1.0,
+            y: 0.0,
+            z: 0.0001,
+        });
+        model.triangles[1].verts = [0, 3, 4];
+        model.triangles.remove(3);
+        model.triangles.push(IndexedTriangle { verts: [1, 2, 3] });
+        model.triangles.push(IndexedTriangle { verts: [0, 1, 4] });
+
+        let report = repair_model(&mut model, &SETTINGS);
+
+        assert_eq!(
+            report,
+            RepairReport {
+                merged_vertices: 1,
+                removed_degenerate_triangles: 1,
+                removed_duplicate_triangles: 1,
+                flipped_triangles: 1,
+                closed_holes: 1,
+            }
+        );
+        assert_eq!(model.vertices.len(), 4);
+        assert_eq!(model.triangles.len(), 4);
+    }
+
+    #[test]
+    fn slivers_below_the_merge_distance_are_degenerate() {
+        let mut model = tetrahedron();
+
+        //The third vertex is off the line through the first two by less than the merge distance
+        model.vertices.push(Vertex {
+            x: 0.5,
+            y: 0.0,
+            z: 0.0000001,
+        });
+        model.triangles.push(IndexedTriangle { verts: [0, 1, 4] });
+
+        let report = repair_model(&mut model, &SETTINGS);
+
+        assert_eq!(report.removed_degenerate_triangles, 1);
+        assert_eq!(model.triangles.len(), 4);
+    }
+
+    #[test]
+    fn merge_distance_must_be_positive() {
+        for merge_distance in [0.0, -0.001, f64::NAN] {
+            let partial = PartialSettings {
+                repair: Some(RepairSettings {
+                    merge_distance,
+                    max_hole_edges: 10,
+                }),
+                ..Default::default()
+            };
+
+            assert!(matches!(
+                partial.get_settings_with_fallback(Settings::default().into()),
+                Err(SlicerErrors::SettingNotPositive { .. })
+            ));
+        }
+    }
+}
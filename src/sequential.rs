@@ -0,0 +1,177 @@
+use crate::plotter::support::Supporter;
+use geo::prelude::*;
+use geo::*;
+use gladius_shared::error::SlicerErrors;
+use gladius_shared::settings::SequentialPrintingSettings;
+use gladius_shared::types::Object;
+use itertools::Itertools;
+
+///Order the objects to be printed one at a time and check that the extruder can print each without hitting the others.
+/// An object taller than the gantry is moved to the end, the order of the other objects is kept.
+/// Objects closer than the extruder clearance radius are never reordered, the extruder would hit one of them in any order so they return an error.
+pub fn order_objects_for_sequential_printing(
+    objects: &mut Vec<Object>,
+    settings: &SequentialPrintingSettings,
+) -> Result<(), SlicerErrors> {
+    let footprints: Vec<Polygon<f64>> = objects.iter().map(get_footprint).collect();
+
+    //The extruder would hit a neighbouring object no matter which is printed first
+    for (first, second) in (0..objects.len()).tuple_combinations() {
+        if get_distance(&footprints[first], &footprints[second])
+            < settings.extruder_clearance_radius
+        {
+            return Err(SlicerErrors::SequentialPrintingCollision {
+                first: objects[first].name.clone(),
+                second: objects[second].name.clone(),
+            });
+        }
+    }
+
+    //The gantry passes over every object printed before the current one
+    let tall_objects: Vec<usize> = objects
+        .iter()
+        .positions(|object| get_height(object) > settings.gantry_height)
+        .collect();
+
+    match tall_objects[..] {
+        [] => {}
+        [tall] => {
+            let object = objects.remove(tall);
+            objects.push(object);
+        }
+        [first, second, ..] => {
+            return Err(SlicerErrors::SequentialPrintingTooTall {
+                first: objects[first].name.clone(),
+                second: objects[second].name.clone(),
+            })
+        }
+    }
+
+    Ok(())
+}
+
+///The convex hull of every layer of the object and its support
+fn get_footprint(object: &Object) -> Polygon<f64> {
+    MultiPoint(
+        object
+            .layers
+            .iter()
+            .flat_map(|slice| {
+                slice
+                    .main_polygon
+                    .0
+                    .iter()
+                    .chain(slice.get_support_polygon().0.iter())
+                    .flat_map(|poly| poly.exterior().0.iter())
+                    .map(|coord| Point(*coord))
+                    .collect::<Vec<Point<f64>>>()
+            })
+            .collect(),
+    )
+    .convex_hull()
+}
+
+///The distance between two convex polygons, the closest points of polygons that don't intersect include a vertex
+fn get_distance(first: &Polygon<f64>, second: &Polygon<f64>) -> f64 {
+    if first.intersects(second) {
+        return 0.0;
+    }
+
+    let vertex_distance = |vertices: &Polygon<f64>, other: &Polygon<f64>| {
+        vertices
+            .exterior()
+            .points_iter()
+            .map(|point| point.euclidean_distance(other))
+            .fold(f64::INFINITY, f64::min)
+    };
+
+    vertex_distance(first, second).min(vertex_distance(second, first))
+}
+
+fn get_height(object: &Object) -> f64 {
+    object
+        .layers
+        .last()
+        .map(|slice| slice.top_height)
+        .unwrap_or(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gladius_shared::settings::Settings;
+    use gladius_shared::types::Slice;
+
+    fn square_object(name: &str, x: f64, layers: usize) -> Object {
+        let settings = Settings::default();
+        let square = [(x, 0.0), (x + 10.0, 0.0), (x + 10.0, 10.0), (x, 10.0)];
+
+        Object {
+            layers: (0..layers)
+                .map(|layer| {
+                    Slice::from_single_point_loop(
+                        square.iter().copied(),
+                        layer as f64,
+                        layer as f64 + 1.0,
+                        layer,
+                        &Settings::default(),
+                    )
+                })
+                .collect(),
+            name: name.to_string(),
+            metadata: vec![],
+            copies: vec![],
+            settings,
+        }
+    }
+
+    #[test]
+    fn tall_object_is_printed_last() {
+        let settings = SequentialPrintingSettings {
+            extruder_clearance_radius: 20.0,
+            gantry_height: 5.0,
+        };
+        let mut objects = vec![
+            square_object("tall", 0.0, 10),
+            square_object("short", 40.0, 3),
+            square_object("other short", 80.0, 3),
+        ];
+
+        order_objects_for_sequential_printing(&mut objects, &settings).unwrap();
+        let names: Vec<&str> = objects.iter().map(|object| object.name.as_str()).collect();
+        assert_eq!(names, vec!["short", "other short", "tall"]);
+
+        objects.push(square_object("another tall", 120.0, 10));
+        assert!(matches!(
+            order_objects_for_sequential_printing(&mut objects, &settings),
+            Err(SlicerErrors::SequentialPrintingTooTall { .. })
+        ));
+    }
+
+    #[test]
+    fn objects_inside_clearance_radius_collide() {
+        let settings = SequentialPrintingSettings {
+            extruder_clearance_radius: 20.0,
+            gantry_height: 50.0,
+        };
+        let mut objects = vec![square_object("a", 0.0, 3), square_object("b", 25.0, 3)];
+
+        assert!(matches!(
+            order_objects_for_sequential_printing(&mut objects, &settings),
+            Err(SlicerErrors::SequentialPrintingCollision { .. })
+        ));
+    }
+
+    #[test]
+    fn objects_at_clearance_radius_are_accepted() {
+        let settings = SequentialPrintingSettings {
+            extruder_clearance_radius: 20.0,
+            gantry_height: 50.0,
+        };
+        let mut objects = vec![square_object("a", 0.0, 3), square_object("b", 30.0, 3)];
+
+        order_objects_for_sequential_printing(&mut objects, &settings).unwrap();
+        let names: Vec<&str> = objects.iter().map(|object| object.name.as_str()).collect();
+        assert_eq!(names, vec!["a", "b"]);
+    }
+}
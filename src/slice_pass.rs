@@ -1,5 +1,5 @@
 use crate::plotter::lightning_infill::lightning_infill;
-use crate::plotter::support::Supporter;
+use crate::plotter::support::{split_support_interface, Supporter};
 use crate::plotter::Plotter;
 use crate::utils::display_state_update;
 use crate::{Object, PolygonOperations, Settings, Slice};
@@ -37,13 +37,52 @@ impl ObjectPass for BrimPass {
                     .collect(),
             );
 
-            objects
+            let first_slice = objects
                 .get_mut(0)
                 .expect("Needs an object")
                 .layers
                 .get_mut(0)
-                .expect("Object needs a Slice")
-                .generate_brim(first_layer_multipolygon, *width);
+                .expect("Object needs a Slice");
+
+            //The brim is printed before the moves already on the layer
+            let existing_chains = std::mem::take(&mut first_slice.fixed_chains);
+            first_slice.generate_brim(first_layer_multipolygon, *width);
+            first_slice.fixed_chains.extend(existing_chains);
+        }
+    }
+}
+
+pub struct CopyPass {}
+
+impl ObjectPass for CopyPass {
+    fn pass(objects: &mut Vec<Object>, _settings: &Settings, send_messages: bool) {
+        if objects.iter().any(|object| !object.copies.is_empty()) {
+            display_state_update("Generating Moves: Copies", send_messages);
+
+            let copies: Vec<Object> = objects
+                .iter_mut()
+                .flat_map(|object| {
+                    let offsets = std::mem::take(&mut object.copies);
+
+                    offsets
+                        .into_iter()
+                        .enumerate()
+                        .map(|(index, offset)| Object {
+                            layers: object
+                                .layers
+                                .par_iter()
+                                .map(|slice| slice.translated_copy(offset))
+                                .collect(),
+                            name: format!("{} copy {}", object.name, index + 1),
+                            metadata: object.metadata.clone(),
+                            copies: vec![],
+                            settings: object.settings.clone(),
+                        })
+                        .collect::<Vec<Object>>()
+                })
+                .collect();
+
+            objects.extend(copies);
         }
     }
 }
@@ -51,20 +90,26 @@ impl ObjectPass for BrimPass {
 pub struct SupportTowerPass {}
 
 impl ObjectPass for SupportTowerPass {
-    fn pass(objects: &mut Vec<Object>, settings: &Settings, send_messages: bool) {
-        if let Some(support) = &settings.support {
+    fn pass(objects: &mut Vec<Object>, _settings: &Settings, send_messages: bool) {
+        if objects.iter().any(|obj| obj.settings.support.is_some()) {
             display_state_update("Generating Support Towers", send_messages);
-            //Add to first object
 
             objects.par_iter_mut().for_each(|obj| {
-                (1..obj.layers.len()).into_iter().rev().for_each(|q| {
-                    //todo Fix this, it feels hacky
-                    if let [ref mut layer, ref mut above, ..] = &mut obj.layers[(q - 1..=q)] {
-                        layer.add_support_polygons(above, support);
-                    } else {
-                        unreachable!()
+                if let Some(support) = &obj.settings.support {
+                    let layers = &mut obj.layers;
+                    (1..layers.len()).into_iter().rev().for_each(|q| {
+                        //todo Fix this, it feels hacky
+                        if let [ref mut layer, ref mut above, ..] = &mut layers[(q - 1..=q)] {
+                            layer.add_support_polygons(above, support);
+                        } else {
+                            unreachable!()
+                        }
+                    });
+
+                    if let Some(interface) = &support.interface {
+                        split_support_interface(layers, interface);
                     }
-                });
+                }
             });
         }
     }
@@ -89,7 +134,7 @@ impl ObjectPass for SkirtPass {
                 .fold(MultiPolygon(vec![]), |a, b| a.union_with(&b))
                 .convex_hull();
 
-            //Add to first object
+            //Add to first object, the skirt is printed before the moves already on the layer
             objects
                 .get_mut(0)
                 .expect("Needs an object")
@@ -97,7 +142,11 @@ impl ObjectPass for SkirtPass {
                 .iter_mut()
                 .take(skirt.layers)
                 .enumerate()
-                .for_each(|(_layer_num, slice)| slice.generate_skirt(&convex_hull, skirt))
+                .for_each(|(_layer_num, slice)| {
+                    let existing_chains = std::mem::take(&mut slice.fixed_chains);
+                    slice.generate_skirt(&convex_hull, skirt);
+                    slice.fixed_chains.extend(existing_chains);
+                })
         }
     }
 }
@@ -126,6 +175,34 @@ impl SlicePass for ShrinkPass {
     }
 }
 
+pub struct VaseModePass {}
+
+impl SlicePass for VaseModePass {
+    fn pass(
+        slices: &mut Vec<Slice>,
+        settings: &Settings,
+        send_messages: bool,
+    ) -> Result<(), SlicerErrors> {
+        if settings.vase_mode {
+            display_state_update("Generating Moves: Vase Mode", send_messages);
+
+            //Each layer starts where the layer below ended so they are done in order
+            let mut previous_end = None;
+            for slice in slices.iter_mut().skip(settings.bottom_layers) {
+                slice.spiral_outer_perimeter(previous_end)?;
+
+                previous_end = slice
+                    .spiral_chain
+                    .as_ref()
+                    .and_then(|chain| chain.moves.last())
+                    .map(|m| m.end)
+                    .or(previous_end);
+            }
+        }
+        Ok(())
+    }
+}
+
 pub struct PerimeterPass {}
 
 impl SlicePass for PerimeterPass {
@@ -267,14 +344,39 @@ impl SlicePass for SupportPass {
         _send_messages: bool,
     ) -> Result<(), SlicerErrors> {
         if let Some(support) = &settings.support {
-            for slice in slices.iter_mut() {
-                slice.fill_support_polygons(support);
+            for (layer_num, slice) in slices.iter_mut().enumerate() {
+                slice.fill_support_polygons(support, layer_num);
             }
         }
         Ok(())
     }
 }
 
+pub struct ModifierPass {}
+
+impl SlicePass for ModifierPass {
+    fn pass(
+        slices: &mut Vec<Slice>,
+        _settings: &Settings,
+        send_messages: bool,
+    ) -> Result<(), SlicerErrors> {
+        if slices
+            .iter()
+            .any(|slice| !slice.modifier_regions.is_empty())
+        {
+            display_state_update("Generating Moves: Modifier Regions", send_messages);
+
+            slices
+                .par_iter_mut()
+                .enumerate()
+                .for_each(|(layer_num, slice)| {
+                    slice.fill_modifier_regions(layer_num);
+                });
+        }
+        Ok(())
+    }
+}
+
 pub struct FillAreaPass {}
 
 impl SlicePass for FillAreaPass {
@@ -1,68 +1,289 @@
+use crate::adaptive::AdaptiveLayerHeights;
+use crate::input::InputModel;
 use crate::*;
 
-pub fn slice(towers: &[TriangleTower], settings: &Settings) -> Result<Vec<Object>, SlicerErrors> {
+///Number of Z ranges per thread that each tower's layers are split into, more ranges balance the work better
+const RANGES_PER_THREAD: usize = 4;
+
+pub fn slice(
+    towers: &[TriangleTower],
+    layer_heights: &[Vec<(f64, f64, f64)>],
+    models: &[InputModel],
+) -> Result<Vec<Object>, SlicerErrors> {
     towers
+        .par_iter()
+        .zip(layer_heights.par_iter())
+        .zip(models.par_iter())
+        .map(|((tower, layer_heights), model)| {
+            let settings = &model.settings;
+            let range_size =
+                (layer_heights.len() / (rayon::current_num_threads() * RANGES_PER_THREAD)).max(1);
+
+            let slices = slice_tower(tower, layer_heights, range_size, settings)
+                .map_err(|error| name_tower_error(error, &model.name))?;
+
+            Ok(Object {
+                layers: slices,
+                name: model.name.clone(),
+                metadata: model.metadata.clone(),
+                copies: model.copies.clone(),
+                settings: settings.clone(),
+            })
+        })
+        .collect()
+}
+
+///The bottom, slice and top height of each layer of each model
+#[cfg(test)]
+type ModelLayerHeights = Vec<Vec<(f64, f64, f64)>>;
+
+///Slice each model with its own settings, returning the layer heights used alongside the objects
+#[cfg(test)]
+pub fn slice_test_models(models: &[InputModel]) -> (ModelLayerHeights, Vec<Object>) {
+    let layer_heights: ModelLayerHeights = models
         .iter()
-        .map(|tower| {
-            let mut tower_iter = TriangleTowerIterator::new(tower);
+        .map(|model| get_layer_heights(model, &model.settings))
+        .collect();
+    let towers = create_towers(models).unwrap();
+    let objects = slice(&towers, &layer_heights, models).unwrap();
 
-            let mut layer = 0.0;
+    (layer_heights, objects)
+}
 
-            let mut first_layer = true;
+///Get the bottom, slicing and top height of every layer up to the first layer above the model.
+/// Layers without a layer height in the layer settings use the adaptive layer height if it's enabled.
+pub fn get_layer_heights(model: &InputModel, settings: &Settings) -> Vec<(f64, f64, f64)> {
+    let max_height = model
+        .vertices
+        .iter()
+        .map(|vertex| vertex.z)
+        .fold(f64::NEG_INFINITY, f64::max);
+    let mut adaptive_layer_heights = settings
+        .adaptive_layer_height
+        .as_ref()
+        .map(|adaptive_settings| AdaptiveLayerHeights::new(model, adaptive_settings));
+    let mut layer_heights = vec![];
+    let mut layer = 0.0;
 
-            let slices: Vec<_> = std::iter::repeat(())
-                .enumerate()
-                .map(|(layer_count, _)| {
-                    //Advance to the correct height
-                    let layer_height = settings.get_layer_settings(layer_count, layer).layer_height;
+    loop {
+        let layer_height = settings
+            .get_layer_height_change(layer_heights.len(), layer)
+            .or_else(|| {
+                adaptive_layer_heights
+                    .as_mut()
+                    .map(|adaptive| adaptive.get_layer_height(layer))
+            })
+            .unwrap_or(settings.layer_height);
 
-                    let bottom_height = layer;
-                    layer += layer_height / 2.0;
-                    tower_iter.advance_to_height(layer)?;
-                    layer += layer_height / 2.0;
+        let bottom_height = layer;
+        layer += layer_height / 2.0;
+        let slice_height = layer;
+        layer += layer_height / 2.0;
+        layer_heights.push((bottom_height, slice_height, layer));
 
-                    let top_height = layer;
+        if slice_height > max_height {
+            return layer_heights;
+        }
+    }
+}
 
-                    first_layer = false;
+///Slice the tower, each Z range of range_size layers is sliced concurrently from an iterator seeded at its bottom.
+/// The slices are the same for any range size and stop at the first empty layer.
+fn slice_tower(
+    tower: &TriangleTower,
+    layer_heights: &[(f64, f64, f64)],
+    range_size: usize,
+    settings: &Settings,
+) -> Result<Vec<Slice>, SlicerErrors> {
+    let range_loops: Vec<Vec<Result<Vec<Vec<Vertex>>, SlicerErrors>>> = layer_heights
+        .par_chunks(range_size)
+        .map(|range| {
+            //Ranges that can't be seeded are walked from the bottom of the tower
+            let mut tower_iter = TriangleTowerIterator::new_at_height(tower, range[0].0)
+                .unwrap_or_else(|_| TriangleTowerIterator::new(tower));
 
-                    //Get the ordered lists of points
-                    Ok((bottom_height, top_height, tower_iter.get_points()))
-                })
-                .take_while(|r| {
-                    if let Ok((_, _, layer_loops)) = r {
-                        !layer_loops.is_empty()
-                    } else {
-                        true
-                    }
-                })
-                .enumerate()
-                .map(|(count, r)| {
-                    match r {
-                        Ok((bot, top, layer_loops)) => {
-                            //Add this slice to the
-                            let slice = Slice::from_multiple_point_loop(
-                                layer_loops
-                                    .iter()
-                                    .map(|verts| {
-                                        verts
-                                            .iter()
-                                            .map(|v| Coordinate { x: v.x, y: v.y })
-                                            .collect::<Vec<Coordinate<f64>>>()
-                                    })
-                                    .collect(),
-                                bot,
-                                top,
-                                count,
-                                settings,
-                            );
-                            slice
-                        }
-                        Err(e) => Err(e),
-                    }
-                })
-                .try_collect()?;
+            let mut loops = vec![];
+            for (_, slice_height, _) in range {
+                let layer_loops = tower_iter
+                    .advance_to_height(*slice_height)
+                    .map(|_| tower_iter.get_points());
+
+                //Layers after an error or an empty layer are never used
+                let done = !matches!(&layer_loops, Ok(layer_loops) if !layer_loops.is_empty());
+                loops.push(layer_loops);
 
-            Ok(Object { layers: slices })
+                if done {
+                    break;
+                }
+            }
+            loops
         })
-        .try_collect()
+        .collect();
+
+    let layer_loops: Vec<Vec<Vec<Vertex>>> = range_loops
+        .into_iter()
+        .flatten()
+        .take_while(|r| {
+            if let Ok(layer_loops) = r {
+                !layer_loops.is_empty()
+            } else {
+                true
+            }
+        })
+        .try_collect()?;
+
+    layer_loops
+        .into_par_iter()
+        .zip(layer_heights.par_iter())
+        .enumerate()
+        .map(|(count, (layer_loops, (bot, _, top)))| {
+            //Add this slice to the
+            let slice = Slice::from_multiple_point_loop(
+                get_line_strings(&layer_loops),
+                *bot,
+                *top,
+                count,
+                settings,
+            );
+
+            //Adaptive layers are not the height in the layer settings
+            slice.map(|mut slice| {
+                if settings.adaptive_layer_height.is_some() {
+                    slice.layer_settings.layer_height = top - bot;
+                }
+                slice
+            })
+        })
+        .collect()
+}
+
+///Get the cross-section of a volume at every layer height, layers outside of the volume are empty
+pub fn slice_volume(
+    tower: &TriangleTower,
+    layer_heights: &[(f64, f64, f64)],
+    settings: &Settings,
+) -> Result<Vec<MultiPolygon<f64>>, SlicerErrors> {
+    let mut tower_iter = TriangleTowerIterator::new(tower);
+
+    layer_heights
+        .iter()
+        .enumerate()
+        .map(|(count, (bot, slice_height, top))| {
+            tower_iter.advance_to_height(*slice_height)?;
+
+            Slice::from_multiple_point_loop(
+                get_line_strings(&tower_iter.get_points()),
+                *bot,
+                *top,
+                count,
+                settings,
+            )
+            .map(|slice| slice.main_polygon)
+        })
+        .collect()
+}
+
+fn get_line_strings(layer_loops: &[Vec<Vertex>]) -> MultiLineString<f64> {
+    layer_loops
+        .iter()
+        .map(|verts| {
+            verts
+                .iter()
+                .map(|v| Coordinate { x: v.x, y: v.y })
+                .collect::<Vec<Coordinate<f64>>>()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tower::create_towers;
+    use std::time::Instant;
+
+    #[test]
+    fn z_ranges_match_sequential_slicing() {
+        for file in ["ring.stl", "5mm_Calibration_Steps.stl", "test_nut.stl"] {
+            let model = InputModel::from_test_file(&format!("test_3D_models/{}", file));
+            let tower = TriangleTower::from_triangles_and_vertices(
+                &model.triangles,
+                model.vertices.clone(),
+            )
+            .unwrap();
+            let settings = Settings::default();
+            let layer_heights = get_layer_heights(&model, &settings);
+
+            let sequential = slice_tower(&tower, &layer_heights, usize::MAX, &settings).unwrap();
+            assert!(!sequential.is_empty());
+
+            for range_size in [1, 3, 10] {
+                let ranges = slice_tower(&tower, &layer_heights, range_size, &settings).unwrap();
+
+                assert_eq!(ranges.len(), sequential.len(), "{}", file);
+                for (a, b) in ranges.iter().zip(sequential.iter()) {
+                    assert_eq!(a.main_polygon, b.main_polygon, "{}", file);
+                    assert_eq!(a.bottom_height, b.bottom_height);
+                    assert_eq!(a.top_height, b.top_height);
+                }
+            }
+        }
+    }
+
+    ///Times the tower creation, advancing the tower through the layers and slicing for the Benchy.
+    /// Each Z range is then sliced on its own, with a thread per range the slicing takes as long as the slowest range.
+    /// Run with `cargo test --release -- --ignored --nocapture benchy_slicing_time`
+    #[test]
+    #[ignore]
+    fn benchy_slicing_time() {
+        let models = vec![InputModel::from_test_file("test_3D_models/3DBenchy.3mf")];
+        let layer_heights = vec![get_layer_heights(&models[0], &models[0].settings)];
+
+        println!("{} threads", rayon::current_num_threads());
+        for _ in 0..5 {
+            let start = Instant::now();
+            let towers = create_towers(&models).unwrap();
+            let tower_time = start.elapsed();
+
+            let start = Instant::now();
+            let mut tower_iter = TriangleTowerIterator::new(&towers[0]);
+            for (_, slice_height, _) in &layer_heights[0] {
+                tower_iter.advance_to_height(*slice_height).unwrap();
+                tower_iter.get_points();
+            }
+            let iterate_time = start.elapsed();
+
+            let start = Instant::now();
+            let objects = slice(&towers, &layer_heights, &models).unwrap();
+            let slice_time = start.elapsed();
+
+            println!(
+                "{} layers, tower creation {:.3}s, iterating {:.3}s, slicing {:.3}s",
+                objects[0].layers.len(),
+                tower_time.as_secs_f64(),
+                iterate_time.as_secs_f64(),
+                slice_time.as_secs_f64()
+            );
+        }
+
+        let tower = &create_towers(&models).unwrap()[0];
+        let settings = &models[0].settings;
+        for ranges in [1, 2, 4, 8, 16] {
+            let range_size = layer_heights[0].len().div_ceil(ranges);
+            let range_times: Vec<f64> = layer_heights[0]
+                .chunks(range_size)
+                .map(|range| {
+                    let start = Instant::now();
+                    slice_tower(tower, range, range.len(), settings).unwrap();
+                    start.elapsed().as_secs_f64()
+                })
+                .collect();
+
+            println!(
+                "{} ranges, total {:.3}s, slowest range {:.3}s",
+                ranges,
+                range_times.iter().sum::<f64>(),
+                range_times.iter().copied().fold(0.0, f64::max)
+            );
+        }
+    }
 }
@@ -1,10 +1,9 @@
+use crate::input::InputModel;
 use crate::SlicerErrors;
 use gladius_shared::types::*;
-use std::cell::RefCell;
+use rayon::prelude::*;
+use std::collections::{HashMap, HashSet};
 use std::fmt::{Display, Formatter};
-use std::hash::{Hash, Hasher};
-use std::ops::Deref;
-use std::rc::Rc;
 
 #[inline]
 fn line_z_intersection(z: f64, v_start: Vertex, v_end: Vertex) -> Vertex {
@@ -55,11 +54,12 @@ impl TriangleTower {
         }
 
         let res_tower_vertices: Result<Vec<TowerVertex>, SlicerErrors> = future_tower_vert
-            .into_iter()
+            .into_par_iter()
             .enumerate()
             .map(|(index, events)| {
-                join_triangle_event(events, index).map(|fragments| TowerVertex {
+                join_triangle_event(events, index).map(|(ring_nodes, fragments)| TowerVertex {
                     start_index: index,
+                    ring_nodes,
                     next_ring_fragments: fragments,
                 })
             })
@@ -67,7 +67,7 @@ impl TriangleTower {
 
         let mut tower_vertices = res_tower_vertices?;
 
-        tower_vertices.sort_by(|a, b| {
+        tower_vertices.par_sort_by(|a, b| {
             vertices[a.start_index]
                 .partial_cmp(&vertices[b.start_index])
                 .expect("STL ERROR: No Points should have NAN values")
@@ -90,110 +90,113 @@ impl TriangleTower {
 
 #[derive(Clone, Debug, PartialEq)]
 struct TowerVertex {
+    ///The fragments that start at this vertex, indexing into ring_nodes
     pub next_ring_fragments: Vec<TowerRing>,
+
+    ///The elements of the fragments that start at this vertex
+    pub ring_nodes: Vec<RingNode>,
     pub start_index: usize,
 }
-#[derive(Clone, Debug, PartialEq)]
+
+///A element of a ring stored in an arena, linked to the next element by its index in the arena
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct RingNode {
+    element: TowerRingElement,
+    next: Option<usize>,
+}
+
+///A ring or fragment of a ring, the first and last elements are indices into an arena of ring nodes
+#[derive(Clone, Copy, Debug, PartialEq)]
 struct TowerRing {
-    pub first_element: Rc<RefCell<TowerRingElement>>,
-    pub last_element: Rc<RefCell<TowerRingElement>>,
+    pub first_element: usize,
+    pub last_element: usize,
 }
 
 impl TowerRing {
-    fn repair_loop(&mut self) -> Result<(), SlicerErrors> {
-        if *self.last_element.borrow() == *self.first_element.borrow() {
-            let mut ring_ptr = self.first_element.clone();
+    fn repair_loop(&mut self, ring_nodes: &mut [RingNode]) -> Result<(), SlicerErrors> {
+        if ring_nodes[self.last_element].element == ring_nodes[self.first_element].element {
+            let mut ring_ptr = self.first_element;
+            let last = ring_nodes[self.last_element].element;
 
             while {
-                let next = if let Some(next) = ring_ptr.borrow().next().cloned() {
-                    next
-                } else {
-                    return Err(SlicerErrors::TowerGeneration);
-                };
-
-                ring_ptr = next;
+                ring_ptr = ring_nodes[ring_ptr]
+                    .next
+                    .ok_or(SlicerErrors::TowerGeneration)?;
 
-                ring_ptr.borrow().next() != Some(&self.last_element)
+                ring_nodes[ring_ptr]
+                    .next
+                    .map(|next| ring_nodes[next].element)
+                    != Some(last)
             } {}
-            let mut borrow = ring_ptr.borrow_mut();
-            borrow.set_next(Some(self.first_element.clone()));
 
-            self.last_element = self.first_element.clone();
+            ring_nodes[ring_ptr].next = Some(self.first_element);
+
+            self.last_element = self.first_element;
         }
 
         Ok(())
     }
 
-    fn join_rings(first: TowerRing, second: TowerRing) -> Result<Self, SlicerErrors> {
-        let second_next = second.first_element.borrow().next_clone();
-
-        first.last_element.borrow_mut().set_next(second_next);
+    fn join_rings(
+        first: TowerRing,
+        second: TowerRing,
+        ring_nodes: &mut [RingNode],
+    ) -> Result<Self, SlicerErrors> {
+        ring_nodes[first.last_element].next = ring_nodes[second.first_element].next;
 
         let mut new_frag = TowerRing {
             first_element: first.first_element,
             last_element: second.last_element,
         };
 
-        new_frag.repair_loop()?;
+        new_frag.repair_loop(ring_nodes)?;
 
         Ok(new_frag)
     }
-    /*
-    fn add_to_end(&mut self, second: TowerRing) {
-        let second_next = second.first_element.borrow().next_clone();
-
-        self.last_element.borrow_mut().set_next(second_next);
-        self.last_element = second.last_element;
-
-        self.repair_loop();
-    }*/
 
-    fn split_on_edge(mut self, edge: usize) -> Result<Vec<Self>, SlicerErrors> {
+    fn split_on_edge(
+        mut self,
+        edge: usize,
+        ring_nodes: &mut [RingNode],
+    ) -> Result<Vec<Self>, SlicerErrors> {
         let mut frags = vec![];
 
-        let mut ring_ptr = self.first_element.clone();
+        let mut ring_ptr = self.first_element;
         let mut last_ptr;
 
-        let mut temp_frag = TowerRing {
-            first_element: self.first_element.clone(),
-            last_element: self.last_element.clone(),
-        };
+        let mut temp_frag = self;
 
         let mut found = false;
 
         while {
-            last_ptr = ring_ptr.clone();
-            let next = ring_ptr
-                .borrow()
-                .next()
-                .ok_or(SlicerErrors::TowerGeneration)?
-                .clone();
-            ring_ptr = next;
-            if let TowerRingElement::Edge { end_index, .. } = *ring_ptr.borrow() {
+            last_ptr = ring_ptr;
+            ring_ptr = ring_nodes[ring_ptr]
+                .next
+                .ok_or(SlicerErrors::TowerGeneration)?;
+
+            if let TowerRingElement::Edge { end_index, .. } = ring_nodes[ring_ptr].element {
                 if end_index == edge {
-                    last_ptr.borrow_mut().set_next(None);
-                    temp_frag.last_element = last_ptr.clone();
+                    ring_nodes[last_ptr].next = None;
+                    temp_frag.last_element = last_ptr;
+
+                    let next = ring_nodes[ring_ptr]
+                        .next
+                        .ok_or(SlicerErrors::TowerGeneration)?;
 
                     frags.push(std::mem::replace(
                         &mut temp_frag,
                         TowerRing {
-                            first_element: ring_ptr
-                                .borrow()
-                                .next_clone()
-                                .ok_or(SlicerErrors::TowerGeneration)?,
-                            last_element: self.last_element.clone(),
+                            first_element: next,
+                            last_element: self.last_element,
                         },
                     ));
-                    self.first_element = ring_ptr
-                        .borrow()
-                        .next_clone()
-                        .ok_or(SlicerErrors::TowerGeneration)?;
+                    self.first_element = next;
 
                     found = true;
                 }
             }
 
-            *ring_ptr.borrow() != *self.last_element.borrow()
+            ring_nodes[ring_ptr].element != ring_nodes[self.last_element].element
         } {}
 
         if found {
@@ -203,76 +206,19 @@ impl TowerRing {
 
         frags.push(self);
 
-        frags.retain(|frag| frag.first_element.borrow().next().is_some());
+        frags.retain(|frag| ring_nodes[frag.first_element].next.is_some());
         Ok(frags)
     }
 }
 
-impl Display for TowerRing {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        if *self.first_element.borrow() == *self.last_element.clone().borrow() {
-            write!(f, "Loop! ")?;
-        }
-
-        match *self.first_element.borrow() {
-            TowerRingElement::Face { triangle_index, .. } => {
-                write!(f, "First F{} ", triangle_index)
-            }
-            TowerRingElement::Edge { end_index, .. } => {
-                write!(f, "First E{} ", end_index)
-            }
-        }?;
-
-        match *self.last_element.borrow() {
-            TowerRingElement::Face { triangle_index, .. } => {
-                write!(f, "Last F{} ", triangle_index)
-            }
-            TowerRingElement::Edge { end_index, .. } => {
-                write!(f, "Last E{} ", end_index)
-            }
-        }?;
-
-        let mut ring_ptr = self.first_element.clone();
-
-        while {
-            match ring_ptr.borrow().deref() {
-                TowerRingElement::Face { triangle_index, .. } => {
-                    write!(f, "F{} ", triangle_index)
-                }
-                TowerRingElement::Edge { end_index, .. } => {
-                    write!(f, "E{} ", end_index)
-                }
-            }?;
-            write!(f, "-> ")?;
-            let next = ring_ptr.borrow().next_clone().expect("Next Must Be valid");
-            ring_ptr = next;
-
-            *ring_ptr.borrow() != *self.last_element.borrow()
-        } {}
-
-        match ring_ptr.borrow().deref() {
-            TowerRingElement::Face { triangle_index, .. } => {
-                write!(f, "F{} ", triangle_index)
-            }
-            TowerRingElement::Edge { end_index, .. } => {
-                write!(f, "E{} ", end_index)
-            }
-        }?;
-
-        write!(f, "")
-    }
-}
-
-#[derive(Clone, Debug, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 enum TowerRingElement {
     Face {
         triangle_index: usize,
-        next: Option<Rc<RefCell<TowerRingElement>>>,
     },
     Edge {
         start_index: usize,
         end_index: usize,
-        next: Option<Rc<RefCell<TowerRingElement>>>,
     },
 }
 
@@ -289,90 +235,6 @@ impl Display for TowerRingElement {
     }
 }
 
-impl TowerRingElement {
-    fn next_clone(&self) -> Option<Rc<RefCell<TowerRingElement>>> {
-        match self {
-            TowerRingElement::Face { next, .. } => next.clone(),
-            TowerRingElement::Edge { next, .. } => next.clone(),
-        }
-    }
-
-    fn next(&self) -> Option<&Rc<RefCell<TowerRingElement>>> {
-        match self {
-            TowerRingElement::Face { next, .. } => next.as_ref(),
-            TowerRingElement::Edge { next, .. } => next.as_ref(),
-        }
-    }
-    fn set_next(&mut self, n: Option<Rc<RefCell<TowerRingElement>>>) {
-        match self {
-            TowerRingElement::Edge { ref mut next, .. } => *next = n,
-            TowerRingElement::Face { ref mut next, .. } => *next = n,
-        }
-    }
-    /*
-    fn deep_clone(&self) -> TowerRingElement {
-        match self {
-            TowerRingElement::Edge {
-                start_index,
-                end_index,
-                ..
-            } => TowerRingElement::Edge {
-                start_index: *start_index,
-                end_index: *end_index,
-                next: None,
-            },
-            TowerRingElement::Face { triangle_index, .. } => TowerRingElement::Face {
-                triangle_index: *triangle_index,
-                next: None,
-            },
-        }
-    }*/
-}
-
-impl PartialEq for TowerRingElement {
-    fn eq(&self, other: &Self) -> bool {
-        match self {
-            TowerRingElement::Edge {
-                end_index,
-                start_index,
-                ..
-            } => match other {
-                TowerRingElement::Edge {
-                    end_index: oei,
-                    start_index: osi,
-                    ..
-                } => end_index == oei && start_index == osi,
-                _ => false,
-            },
-            TowerRingElement::Face { triangle_index, .. } => match other {
-                TowerRingElement::Face {
-                    triangle_index: oti,
-                    ..
-                } => oti == triangle_index,
-                _ => false,
-            },
-        }
-    }
-}
-
-impl Hash for TowerRingElement {
-    fn hash<H: Hasher>(&self, state: &mut H) {
-        match self {
-            TowerRingElement::Edge {
-                end_index,
-                start_index,
-                ..
-            } => {
-                end_index.hash(state);
-                start_index.hash(state);
-            }
-            TowerRingElement::Face { triangle_index, .. } => {
-                triangle_index.hash(state);
-            }
-        }
-    }
-}
-
 #[derive(Clone, Debug, PartialEq)]
 pub enum TriangleEvent {
     MiddleVertex {
@@ -393,128 +255,89 @@ pub enum TriangleEvent {
 fn join_triangle_event(
     events: Vec<TriangleEvent>,
     starting_point: usize,
-) -> Result<Vec<TowerRing>, SlicerErrors> {
-    //debug!("Tri events = {:?}",events);
+) -> Result<(Vec<RingNode>, Vec<TowerRing>), SlicerErrors> {
+    let mut ring_nodes: Vec<RingNode> = Vec::new();
     let mut element_list: Vec<TowerRing> = Vec::new();
+
+    //Add the elements in order, each linked to the one after it
+    let mut add_fragment = |elements: &[TowerRingElement]| {
+        let first_element = ring_nodes.len();
+
+        ring_nodes.extend(
+            elements
+                .iter()
+                .enumerate()
+                .map(|(index, element)| RingNode {
+                    element: *element,
+                    next: if index + 1 < elements.len() {
+                        Some(first_element + index + 1)
+                    } else {
+                        None
+                    },
+                }),
+        );
+
+        element_list.push(TowerRing {
+            first_element,
+            last_element: ring_nodes.len() - 1,
+        });
+    };
+
     for event in events.iter() {
         match event {
             TriangleEvent::LeadingEdge {
                 leading_edge,
                 triangle,
-            } => {
-                let triangle_element = Rc::new(RefCell::new(TowerRingElement::Face {
-                    triangle_index: *triangle,
-                    next: None,
-                }));
-                let edge_element = Rc::new(RefCell::new(TowerRingElement::Edge {
+            } => add_fragment(&[
+                TowerRingElement::Edge {
                     start_index: starting_point,
                     end_index: *leading_edge,
-                    next: Some(triangle_element.clone()),
-                }));
-                let new_ring = TowerRing {
-                    first_element: edge_element,
-                    last_element: triangle_element,
-                };
-                element_list.push(new_ring);
-            }
+                },
+                TowerRingElement::Face {
+                    triangle_index: *triangle,
+                },
+            ]),
             TriangleEvent::TrailingEdge {
                 triangle,
                 trailing_edge,
-            } => {
-                let edge_element = Rc::new(RefCell::new(TowerRingElement::Edge {
+            } => add_fragment(&[
+                TowerRingElement::Face {
+                    triangle_index: *triangle,
+                },
+                TowerRingElement::Edge {
                     start_index: starting_point,
                     end_index: *trailing_edge,
-                    next: None,
-                }));
-                let triangle_element = Rc::new(RefCell::new(TowerRingElement::Face {
-                    triangle_index: *triangle,
-                    next: Some(edge_element.clone()),
-                }));
-                let new_ring = TowerRing {
-                    first_element: triangle_element,
-                    last_element: edge_element,
-                };
-                element_list.push(new_ring);
-            }
+                },
+            ]),
             TriangleEvent::MiddleVertex {
                 leading_edge,
                 triangle,
                 trailing_edge,
-            } => {
-                let trail_edge_element = Rc::new(RefCell::new(TowerRingElement::Edge {
+            } => add_fragment(&[
+                TowerRingElement::Edge {
                     start_index: starting_point,
-                    end_index: *trailing_edge,
-                    next: None,
-                }));
-                let triangle_element = Rc::new(RefCell::new(TowerRingElement::Face {
+                    end_index: *leading_edge,
+                },
+                TowerRingElement::Face {
                     triangle_index: *triangle,
-                    next: Some(trail_edge_element.clone()),
-                }));
-                let lead_edge_element = Rc::new(RefCell::new(TowerRingElement::Edge {
+                },
+                TowerRingElement::Edge {
                     start_index: starting_point,
-                    end_index: *leading_edge,
-                    next: Some(triangle_element.clone()),
-                }));
-                let new_ring = TowerRing {
-                    first_element: lead_edge_element,
-                    last_element: trail_edge_element,
-                };
-                element_list.push(new_ring);
-            }
-        }
-    }
-
-    join_fragments(&mut element_list)?;
-
-    Ok(element_list)
-}
-
-/*
-fn join_fragments(fragments: &mut Vec<TowerRing>) {
-
-    let mut hm = HashMap::new();
-    let mut frags =  fragments.clone();
-    for frag in fragments.drain(..){
-        let last = frag.last_element.borrow().clone();
-        hm.insert(last, frag);
-    }
-
-    let mut found = true;
-
-    while found {
-        for frag in fragments.drain(..){
-            let last = frag.last_element.borrow().clone();
-            hm.insert(last, frag);
-        }
-        found = false;
-        for frag in frags.drain(..) {
-            let first_el = frag.first_element.borrow().clone();
-            let last_el = frag.last_element.borrow().clone();
-            if first_el != last_el {
-                if let Some(mut first) = hm.remove(&first_el) {
-                    println!("here");
-
-                    first.add_to_end(frag);
-                    hm.remove(&last_el);
-                    hm.insert(last_el, first);
-                    found = true;
-                }
-            }
+                    end_index: *trailing_edge,
+                },
+            ]),
         }
-        frags.extend(hm.values().map(|v|  v.clone()));
     }
 
-    fragments.extend(hm.drain().map(|(k,v)| v));
+    join_fragments(&mut element_list, &mut ring_nodes)?;
 
+    Ok((ring_nodes, element_list))
 }
-*/
-fn join_fragments(fragments: &mut Vec<TowerRing>) -> Result<(), SlicerErrors> {
-    /*
 
-        for frag in &*fragments{
-            println!("fragment {}",frag);
-        }
-    */
+fn join_fragments(
+    fragments: &mut Vec<TowerRing>,
+    ring_nodes: &mut [RingNode],
+) -> Result<(), SlicerErrors> {
     'outer: loop {
         for first_pos in 0..fragments.len() {
             for second_pos in (first_pos + 1)..fragments.len() {
@@ -525,11 +348,13 @@ fn join_fragments(fragments: &mut Vec<TowerRing>) -> Result<(), SlicerErrors> {
                     .get(second_pos)
                     .ok_or(SlicerErrors::TowerGeneration)?;
 
-                if first.last_element == second.first_element {
+                if ring_nodes[first.last_element].element
+                    == ring_nodes[second.first_element].element
+                {
                     let second_r = fragments.swap_remove(second_pos);
                     let first_r = fragments.swap_remove(first_pos);
 
-                    fragments.push(TowerRing::join_rings(first_r, second_r)?);
+                    fragments.push(TowerRing::join_rings(first_r, second_r, ring_nodes)?);
 
                     continue 'outer;
                 }
@@ -541,11 +366,15 @@ fn join_fragments(fragments: &mut Vec<TowerRing>) -> Result<(), SlicerErrors> {
     }
 }
 
+#[derive(Clone)]
 pub struct TriangleTowerIterator<'s> {
     tower: &'s TriangleTower,
     tower_vert_index: usize,
     z_height: f64,
     active_rings: Vec<TowerRing>,
+
+    ///Arena of all the ring elements added to the active rings
+    ring_nodes: Vec<RingNode>,
 }
 
 impl<'s> TriangleTowerIterator<'s> {
@@ -556,22 +385,125 @@ impl<'s> TriangleTowerIterator<'s> {
             tower,
             tower_vert_index: 0,
             active_rings: vec![],
+            ring_nodes: vec![],
         }
     }
 
+    ///Create an iterator at the given height without advancing through the layers below it.
+    /// The rings are built from the links between the faces and the edges that cross the height, meshes with edges shared by more than 2 faces return an error.
+    pub fn new_at_height(tower: &'s TriangleTower, z: f64) -> Result<Self, SlicerErrors> {
+        let tower_vert_index = tower
+            .tower_vertices
+            .partition_point(|tower_vertex| tower.vertices[tower_vertex.start_index].z < z);
+
+        //Edges that end at a vertex below the height have been removed
+        let is_active = |element: TowerRingElement| match element {
+            TowerRingElement::Edge { end_index, .. } => tower.vertices[end_index].z >= z,
+            TowerRingElement::Face { .. } => true,
+        };
+
+        let mut links: HashMap<TowerRingElement, TowerRingElement> = HashMap::new();
+        let mut linked_to: HashSet<TowerRingElement> = HashSet::new();
+
+        for tower_vertex in &tower.tower_vertices[..tower_vert_index] {
+            for fragment in &tower_vertex.next_ring_fragments {
+                let last = tower_vertex.ring_nodes[fragment.last_element].element;
+                let mut ring_ptr = fragment.first_element;
+
+                //Walked the same way as the rings are split, up to the last element
+                while let Some(next) = tower_vertex.ring_nodes[ring_ptr].next {
+                    let element = tower_vertex.ring_nodes[ring_ptr].element;
+                    let next_element = tower_vertex.ring_nodes[next].element;
+
+                    //Elements can only be seeded if each is in one ring once, which isn't true if faces share edges
+                    if is_active(element)
+                        && is_active(next_element)
+                        && (links.insert(element, next_element).is_some()
+                            || !linked_to.insert(next_element))
+                    {
+                        return Err(SlicerErrors::TowerGeneration);
+                    }
+
+                    if next_element == last {
+                        break;
+                    }
+                    ring_ptr = next;
+                }
+            }
+        }
+
+        //Sorted so the arena is the same every time
+        let mut starts: Vec<TowerRingElement> = links.keys().copied().collect();
+        starts.sort_by_key(|element| match *element {
+            TowerRingElement::Face { triangle_index } => (0, triangle_index, 0),
+            TowerRingElement::Edge {
+                start_index,
+                end_index,
+            } => (1, start_index, end_index),
+        });
+
+        let mut ring_nodes: Vec<RingNode> = vec![];
+        let mut active_rings = vec![];
+        let mut visited = HashSet::new();
+
+        for start in starts {
+            if visited.contains(&start) {
+                continue;
+            }
+
+            let first_element = ring_nodes.len();
+            let mut element = start;
+
+            loop {
+                if !visited.insert(element) {
+                    return Err(SlicerErrors::TowerGeneration);
+                }
+
+                let next = *links.get(&element).ok_or(SlicerErrors::TowerGeneration)?;
+
+                ring_nodes.push(RingNode {
+                    element,
+                    next: Some(ring_nodes.len() + 1),
+                });
+
+                if next == start {
+                    break;
+                }
+                element = next;
+            }
+
+            let last = ring_nodes.len() - 1;
+            ring_nodes[last].next = Some(first_element);
+
+            active_rings.push(TowerRing {
+                first_element,
+                last_element: first_element,
+            });
+        }
+
+        Ok(Self {
+            z_height: z,
+            tower,
+            tower_vert_index,
+            active_rings,
+            ring_nodes,
+        })
+    }
+
     pub fn advance_to_height(&mut self, z: f64) -> Result<(), SlicerErrors> {
-        //println!("Advance to height {} {} {}", self.tower.get_height_of_vertex(self.tower_vert_index), z, self.tower.tower_vertices[self.tower_vert_index].start_index);
+        let start_vert_index = self.tower_vert_index;
 
         while self.tower.get_height_of_vertex(self.tower_vert_index) < z
             && self.tower.tower_vertices.len() + 1 != self.tower_vert_index
         {
-            let pop_tower_vert = self.tower.tower_vertices[self.tower_vert_index].clone();
+            let pop_tower_vert = &self.tower.tower_vertices[self.tower_vert_index];
 
             //Create Frags from rings by removing current edges
+            let ring_nodes = &mut self.ring_nodes;
             let vec_frag: Result<Vec<Vec<TowerRing>>, SlicerErrors> = self
                 .active_rings
                 .drain(..)
-                .map(|tower_ring| tower_ring.split_on_edge(pop_tower_vert.start_index))
+                .map(|tower_ring| tower_ring.split_on_edge(pop_tower_vert.start_index, ring_nodes))
                 .collect();
 
             let mut frags: Vec<TowerRing> = vec_frag?
@@ -579,66 +511,272 @@ impl<'s> TriangleTowerIterator<'s> {
                 .flat_map(|vec_frag| vec_frag.into_iter())
                 .collect();
 
-            //Add the new fragments
+            //Add the new fragments, moving their elements into this iterator's arena
+            let offset = self.ring_nodes.len();
 
-            frags.extend(pop_tower_vert.next_ring_fragments.clone().into_iter());
+            self.ring_nodes
+                .extend(pop_tower_vert.ring_nodes.iter().map(|node| RingNode {
+                    element: node.element,
+                    next: node.next.map(|next| next + offset),
+                }));
 
-            join_fragments(&mut frags)?;
+            frags.extend(
+                pop_tower_vert
+                    .next_ring_fragments
+                    .iter()
+                    .map(|fragment| TowerRing {
+                        first_element: fragment.first_element + offset,
+                        last_element: fragment.last_element + offset,
+                    }),
+            );
+
+            join_fragments(&mut frags, &mut self.ring_nodes)?;
             self.active_rings = frags;
             self.tower_vert_index += 1;
         }
 
+        if self.tower_vert_index != start_vert_index {
+            self.compact_ring_nodes();
+        }
+
         self.z_height = z;
 
         Ok(())
     }
 
+    ///Move the elements of the active rings to a new arena, the elements removed from the rings are dropped
+    fn compact_ring_nodes(&mut self) {
+        let mut new_indices = vec![None; self.ring_nodes.len()];
+        let mut ring_nodes = Vec::with_capacity(self.ring_nodes.len() / 2);
+
+        for ring in &mut self.active_rings {
+            let mut ring_ptr = Some(ring.first_element);
+
+            while let Some(index) = ring_ptr.filter(|index| new_indices[*index].is_none()) {
+                new_indices[index] = Some(ring_nodes.len());
+                ring_nodes.push(self.ring_nodes[index]);
+                ring_ptr = self.ring_nodes[index].next;
+            }
+
+            ring.first_element = new_indices[ring.first_element].expect("First element is moved");
+            ring.last_element =
+                new_indices[ring.last_element].expect("Rings end at their last element");
+        }
+
+        for node in &mut ring_nodes {
+            node.next = node
+                .next
+                .map(|next| new_indices[next].expect("Linked elements are moved"));
+        }
+
+        self.ring_nodes = ring_nodes;
+    }
+
+    ///Get the loops at the current height.
+    /// Each loop starts at its lowest edge and the loops are sorted by it, so the points don't depend on how the iterator reached this height.
     pub fn get_points(&self) -> Vec<Vec<Vertex>> {
-        let mut points_vec = vec![];
+        let mut loops = vec![];
         for ring in &self.active_rings {
-            let mut points = vec![];
-            let mut ring_ptr = ring.first_element.clone();
+            let mut edges = vec![];
+            let mut ring_ptr = ring.first_element;
             while {
                 if let TowerRingElement::Edge {
                     start_index,
                     end_index,
-                    ..
-                } = ring_ptr.borrow().deref()
+                } = self.ring_nodes[ring_ptr].element
                 {
-                    points.push(line_z_intersection(
-                        self.z_height,
-                        self.tower.vertices[*start_index],
-                        self.tower.vertices[*end_index],
-                    ));
+                    edges.push((start_index, end_index));
                 }
-                let next = ring_ptr
-                    .borrow()
-                    .next()
-                    .expect("Rings must be complete Loops")
-                    .clone();
 
-                ring_ptr = next;
+                ring_ptr = self.ring_nodes[ring_ptr]
+                    .next
+                    .expect("Rings must be complete Loops");
 
-                ring_ptr != ring.last_element
+                self.ring_nodes[ring_ptr].element != self.ring_nodes[ring.last_element].element
             } {}
 
-            let first_point = points[0];
+            let lowest_edge = edges
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, edge)| **edge)
+                .map(|(index, _)| index)
+                .expect("Rings must contain edges");
 
-            points.push(first_point);
-            points_vec.push(points);
+            edges.rotate_left(lowest_edge);
+            loops.push(edges);
         }
 
-        points_vec
+        loops.sort_by_key(|edges| edges[0]);
+
+        loops
+            .into_iter()
+            .map(|edges| {
+                let mut points: Vec<Vertex> = edges
+                    .iter()
+                    .map(|(start_index, end_index)| {
+                        line_z_intersection(
+                            self.z_height,
+                            self.tower.vertices[*start_index],
+                            self.tower.vertices[*end_index],
+                        )
+                    })
+                    .collect();
+
+                let first_point = points[0];
+
+                points.push(first_point);
+                points
+            })
+            .collect()
     }
 }
 
-pub fn create_towers(
-    models: &[(Vec<Vertex>, Vec<IndexedTriangle>)],
-) -> Result<Vec<TriangleTower>, SlicerErrors> {
+pub fn create_towers(models: &[InputModel]) -> Result<Vec<TriangleTower>, SlicerErrors> {
     models
-        .iter()
-        .map(|(vertices, triangles)| {
-            TriangleTower::from_triangles_and_vertices(triangles, vertices.clone())
+        .par_iter()
+        .map(|model| {
+            TriangleTower::from_triangles_and_vertices(&model.triangles, model.vertices.clone())
+                .map_err(|error| name_tower_error(error, &model.name))
         })
         .collect()
 }
+
+///Name the object in tower generation errors
+pub fn name_tower_error(error: SlicerErrors, name: &str) -> SlicerErrors {
+    match error {
+        SlicerErrors::TowerGeneration => SlicerErrors::ObjectTowerGeneration {
+            name: name.to_string(),
+        },
+        error => error,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn tower_is_send_and_sync() {
+        assert_send_sync::<TriangleTower>();
+    }
+
+    ///The loops at the height and twice their signed areas
+    fn loops_at(tower_iter: &mut TriangleTowerIterator, z: f64) -> Vec<(Vec<Vertex>, f64)> {
+        tower_iter.advance_to_height(z).unwrap();
+        tower_iter
+            .get_points()
+            .into_iter()
+            .map(|points| {
+                let area = points
+                    .windows(2)
+                    .map(|pair| pair[0].x * pair[1].y - pair[1].x * pair[0].y)
+                    .sum();
+                (points, area)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn cube_is_one_closed_square_loop() {
+        let model = InputModel::cuboid("cube", [0.0, 0.0, 0.0], [10.0, 10.0, 10.0]);
+        let tower =
+            TriangleTower::from_triangles_and_vertices(&model.triangles, model.vertices).unwrap();
+        let mut tower_iter = TriangleTowerIterator::new(&tower);
+
+        for layer in 0..10 {
+            let loops = loops_at(&mut tower_iter, layer as f64 + 0.5);
+            assert_eq!(loops.len(), 1);
+
+            let (points, area) = &loops[0];
+            assert_eq!(points.first(), points.last());
+            assert!((area.abs() - 200.0).abs() < 0.000_1);
+            for point in points {
+                let on_side = |value: f64| value.abs() < 0.000_1 || (value - 10.0).abs() < 0.000_1;
+                assert!(on_side(point.x) || on_side(point.y));
+            }
+        }
+
+        assert!(loops_at(&mut tower_iter, 10.5).is_empty());
+    }
+
+    #[test]
+    fn rings_are_added_and_removed_at_vertices() {
+        //Two separate boxes, the second starts below the top of the first
+        let low = InputModel::cuboid("low", [0.0, 0.0, 0.0], [10.0, 10.0, 5.0]);
+        let high = InputModel::cuboid("high", [20.0, 0.0, 3.0], [30.0, 10.0, 8.0]);
+        let mut vertices = low.vertices.clone();
+        vertices.extend(high.vertices);
+        let mut triangles = low.triangles;
+        triangles.extend(high.triangles.iter().map(|triangle| IndexedTriangle {
+            verts: triangle.verts.map(|index| index + 8),
+        }));
+
+        let tower = TriangleTower::from_triangles_and_vertices(&triangles, vertices).unwrap();
+        let mut tower_iter = TriangleTowerIterator::new(&tower);
+
+        for (z, count) in [(1.0, 1), (4.0, 2), (6.0, 1), (9.0, 0)] {
+            let loops = loops_at(&mut tower_iter, z);
+            assert_eq!(loops.len(), count, "{}", z);
+            for (_, area) in loops {
+                assert!((area.abs() - 200.0).abs() < 0.000_1);
+            }
+        }
+    }
+
+    #[test]
+    fn ring_has_an_outer_loop_and_a_hole() {
+        let model = InputModel::from_test_file("test_3D_models/ring.stl");
+        let tower =
+            TriangleTower::from_triangles_and_vertices(&model.triangles, model.vertices).unwrap();
+        let mut tower_iter = TriangleTowerIterator::new(&tower);
+
+        for layer in 0..30 {
+            let loops = loops_at(&mut tower_iter, 0.1 + 0.2 * layer as f64);
+            assert_eq!(loops.len(), 2);
+
+            //The hole is wound the other way around
+            assert!(loops[0].1 * loops[1].1 < 0.0);
+        }
+    }
+
+    #[test]
+    fn seeded_iterator_matches_the_advanced_iterator() {
+        for file in ["ring.stl", "5mm_Calibration_Steps.stl", "test_nut.stl"] {
+            let model = InputModel::from_test_file(&format!("test_3D_models/{}", file));
+            let tower =
+                TriangleTower::from_triangles_and_vertices(&model.triangles, model.vertices)
+                    .unwrap();
+            let mut tower_iter = TriangleTowerIterator::new(&tower);
+
+            for layer in 0..60 {
+                let z = 0.1 + 0.2 * layer as f64;
+                let mut seed = TriangleTowerIterator::new_at_height(&tower, z - 0.1).unwrap();
+
+                assert_eq!(
+                    loops_at(&mut seed, z),
+                    loops_at(&mut tower_iter, z),
+                    "{}",
+                    file
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn cloned_iterator_continues_like_the_original() {
+        let model = InputModel::from_test_file("test_3D_models/test_nut.stl");
+        let tower =
+            TriangleTower::from_triangles_and_vertices(&model.triangles, model.vertices).unwrap();
+
+        let mut tower_iter = TriangleTowerIterator::new(&tower);
+        tower_iter.advance_to_height(0.1 + 0.2 * 20.0).unwrap();
+        let mut seed = tower_iter.clone();
+
+        for layer in 21..60 {
+            let z = 0.1 + 0.2 * layer as f64;
+            assert_eq!(loops_at(&mut seed, z), loops_at(&mut tower_iter, z));
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use crate::input::InputModel;
+use crate::plotter::polygon_operations::PolygonOperations;
+use crate::slicing::slice_volume;
+use crate::tower::{name_tower_error, TriangleTower};
+use gladius_shared::error::SlicerErrors;
+use gladius_shared::types::{Object, VolumeType};
+use rayon::prelude::*;
+
+///Apply the cross-section of each volume to every layer of the objects it overlaps.
+/// Negative volumes are cut out of the layer, support enforcers and blockers mark where support is forced or removed
+/// and modifiers add a region with the layer settings of that layer changed by the modifier.
+/// Later modifiers are applied inside the regions left by earlier ones.
+pub fn apply_volumes(
+    objects: &mut [Object],
+    layer_heights: &[Vec<(f64, f64, f64)>],
+    volumes: &[InputModel],
+    towers: &[TriangleTower],
+) -> Result<(), SlicerErrors> {
+    for (volume, tower) in volumes.iter().zip(towers) {
+        objects
+            .par_iter_mut()
+            .zip(layer_heights.par_iter())
+            .try_for_each(|(object, layer_heights)| {
+                let regions = slice_volume(tower, layer_heights, &object.settings)
+                    .map_err(|error| name_tower_error(error, &volume.name))?;
+
+                for (slice, region) in object.layers.iter_mut().zip(regions) {
+                    if region.0.is_empty() {
+                        continue;
+                    }
+
+                    match &volume.volume {
+                        VolumeType::Part => {}
+                        VolumeType::Modifier(changes) => {
+                            let layer_settings = slice.layer_settings.with_changes(changes);
+                            slice.modifier_regions.push((region, layer_settings));
+                        }
+                        VolumeType::NegativeVolume => {
+                            slice.main_polygon = slice.main_polygon.difference_with(&region);
+                            slice.remaining_area = slice.remaining_area.difference_with(&region);
+                        }
+                        VolumeType::SupportEnforcer => {
+                            slice.support_enforcers = slice.support_enforcers.union_with(&region);
+                        }
+                        VolumeType::SupportBlocker => {
+                            slice.support_blockers = slice.support_blockers.union_with(&region);
+                        }
+                    }
+                }
+
+                Ok(())
+            })?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::slicing::slice_test_models;
+    use crate::tower::create_towers;
+    use geo::prelude::*;
+    use gladius_shared::settings::PartialLayerSettings;
+
+    ///Slice the parts then apply the volumes to them
+    fn slice_with_volumes(models: &[InputModel], volumes: &[InputModel]) -> Vec<Object> {
+        let (layer_heights, mut objects) = slice_test_models(models);
+
+        apply_volumes(
+            &mut objects,
+            &layer_heights,
+            volumes,
+            &create_towers(volumes).unwrap(),
+        )
+        .unwrap();
+
+        objects
+    }
+
+    #[test]
+    fn modifier_regions_are_added_where_volume_overlaps() {
+        let models = vec![InputModel::cuboid(
+            "part",
+            [0.0, 0.0, 0.0],
+            [20.0, 20.0, 6.0],
+        )];
+        let volumes = vec![InputModel {
+            volume: VolumeType::Modifier(Box::new(PartialLayerSettings {
+                infill_percentage: Some(0.8),
+                ..PartialLayerSettings::default()
+            })),
+            ..InputModel::cuboid("modifier", [5.0, 5.0, 2.0], [10.0, 10.0, 4.0])
+        }];
+
+        let objects = slice_with_volumes(&models, &volumes);
+
+        for slice in &objects[0].layers {
+            let inside = slice.get_height() > 2.0 && slice.get_height() < 4.0;
+            assert_eq!(slice.modifier_regions.len(), usize::from(inside));
+
+            if let Some((region, layer_settings)) = slice.modifier_regions.first() {
+                assert!((region.unsigned_area() - 25.0).abs() < 0.000_1);
+                assert_eq!(layer_settings.infill_percentage, 0.8);
+            }
+        }
+    }
+
+    #[test]
+    fn negative_volumes_are_cut_out_of_parts() {
+        let models = vec![InputModel::cuboid(
+            "part",
+            [0.0, 0.0, 0.0],
+            [20.0, 20.0, 6.0],
+        )];
+        let volumes = vec![InputModel {
+            volume: VolumeType::NegativeVolume,
+            ..InputModel::cuboid("hole", [5.0, 5.0, -1.0], [10.0, 10.0, 7.0])
+        }];
+
+        let objects = slice_with_volumes(&models, &volumes);
+
+        for slice in &objects[0].layers {
+            assert!((slice.main_polygon.unsigned_area() - 375.0).abs() < 0.000_1);
+            assert_eq!(slice.main_polygon.0[0].interiors().len(), 1);
+            assert!(slice.modifier_regions.is_empty());
+        }
+    }
+}